@@ -0,0 +1,199 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use chrono::{Local, TimeZone};
+
+use crate::domain::Data;
+use crate::domain::nodes::Traffic;
+
+pub const TRAFFIC_FILE: &str = "traffic.json";
+
+const DAYS_KEPT: usize = 400;
+/// Seconds between saves while the counters keep changing. The counters
+/// of the last poll are saved too, so a tincmgr restart loses nothing.
+const SAVE_INTERVAL: i64 = 300;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeAccount {
+    /// Counters of the previous poll.
+    pub last:       Option<Traffic>,
+    pub resets:     usize,
+    pub total:      Traffic,
+    /// Keyed by `YYYY-MM-DD` and `YYYY-MM` in local time.
+    pub days:       BTreeMap<String, Traffic>,
+    pub months:     BTreeMap<String, Traffic>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    Month,
+    Total,
+}
+impl Period {
+    pub fn parse(period: &str) -> Option<Self> {
+        match period {
+            "day" => Some(Period::Day),
+            "month" => Some(Period::Month),
+            "total" => Some(Period::Total),
+            _ => None,
+        }
+    }
+
+    /// The day or month `time` falls in.
+    pub fn key(self, time: i64) -> String {
+        let format = match self {
+            Period::Day => "%Y-%m-%d",
+            Period::Month => "%Y-%m",
+            Period::Total => return String::new(),
+        };
+        Local.timestamp_opt(time, 0).single()
+            .map(|x| x.format(format).to_string())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeUsage {
+    pub name:       String,
+    #[serde(flatten)]
+    pub traffic:    Traffic,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct UsageReport {
+    pub period:     Period,
+    /// The day or month reported, empty for totals.
+    pub key:        String,
+    pub total:      Traffic,
+    /// Top talkers first.
+    pub nodes:      Vec<NodeUsage>,
+}
+
+/// The totals to write to the accounting file.
+#[must_use]
+pub struct TrafficWrite {
+    path:           PathBuf,
+    json:           String,
+}
+impl TrafficWrite {
+    pub fn save(self) -> Result<()> {
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, self.json)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+/// Per node traffic totals kept across tincd and tincmgr restarts.
+#[derive(Default, Serialize, Deserialize)]
+pub struct TrafficAccounting {
+    #[serde(skip)]
+    path:           Option<PathBuf>,
+    #[serde(skip)]
+    changed:        bool,
+    #[serde(skip)]
+    last_save:      i64,
+    /// pid of the tincd the `last` counters came from.
+    pub pid:        Option<i32>,
+    pub nodes:      BTreeMap<String, NodeAccount>,
+}
+impl TrafficAccounting {
+    /// Load the totals saved in `path`, or start empty if there are none.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut accounting = match fs::read_to_string(path) {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => TrafficAccounting::default(),
+            Err(e) => return Err(e),
+        };
+        accounting.path = Some(path.to_path_buf());
+        Ok(accounting)
+    }
+
+    /// Add what every node sent and received since the previous poll. The
+    /// totals to save are returned when they changed and were not saved
+    /// for a while, or at once after a reset.
+    pub fn update(&mut self, data: &Data, time: i64) -> Result<Option<TrafficWrite>> {
+        let pid = data.daemon.as_ref().map(|x| x.pid);
+        let restarted = self.pid.is_some() && pid != self.pid;
+        let mut reset = pid != self.pid;
+        self.pid = pid;
+        let day = Period::Day.key(time);
+        let month = Period::Month.key(time);
+        for node in data.nodes.iter() {
+            let traffic = match node.traffic {
+                Some(ref traffic) => traffic,
+                None => continue,
+            };
+            let account = self.nodes.entry(node.name.clone()).or_default();
+            let delta = match account.last {
+                // The first poll only sets the baseline.
+                None => Traffic::default(),
+                Some(ref last) => match traffic.since(last) {
+                    Some(delta) if !restarted => delta,
+                    _ => {
+                        info!("Traffic counters of {} were reset", node.name);
+                        account.resets += 1;
+                        reset = true;
+                        traffic.clone()
+                    }
+                },
+            };
+            if account.last.as_ref() != Some(traffic) {
+                self.changed = true;
+            }
+            account.last = Some(traffic.clone());
+            account.total.add(&delta);
+            account.days.entry(day.clone()).or_default().add(&delta);
+            account.months.entry(month.clone()).or_default().add(&delta);
+            while account.days.len() > DAYS_KEPT {
+                let oldest = account.days.keys().next().cloned().unwrap_or_default();
+                account.days.remove(&oldest);
+            }
+        }
+
+        if reset || (self.changed && time - self.last_save >= SAVE_INTERVAL) {
+            self.last_save = time;
+            return self.write();
+        }
+        Ok(None)
+    }
+
+    /// The current totals to save, if there is a file to save them to.
+    pub fn write(&mut self) -> Result<Option<TrafficWrite>> {
+        let path = match self.path {
+            Some(ref path) => path.clone(),
+            None => return Ok(None),
+        };
+        let json = serde_json::to_string(self).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        self.changed = false;
+        Ok(Some(TrafficWrite { path, json }))
+    }
+
+    /// Usage of every node in the day or month `key`, largest first.
+    pub fn report(&self, period: Period, key: &str) -> UsageReport {
+        let mut nodes: Vec<NodeUsage> = self.nodes.iter()
+            .filter_map(|(name, account)| {
+                let traffic = match period {
+                    Period::Day => account.days.get(key)?,
+                    Period::Month => account.months.get(key)?,
+                    Period::Total => &account.total,
+                };
+                Some(NodeUsage { name: name.clone(), traffic: traffic.clone() })
+            })
+            .collect();
+        nodes.sort_by(|a, b| b.traffic.bytes().cmp(&a.traffic.bytes()).then(a.name.cmp(&b.name)));
+        let mut total = Traffic::default();
+        for node in nodes.iter() {
+            total.add(&node.traffic);
+        }
+        UsageReport {
+            period,
+            key: key.to_string(),
+            total,
+            nodes,
+        }
+    }
+}
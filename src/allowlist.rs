@@ -0,0 +1,152 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+use crate::domain::Data;
+use crate::events::{Event, EventKind};
+
+/// Node names tinc accepts: letters, digits and underscores.
+pub fn is_node_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// The node names expected in the mesh.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Allowlist {
+    pub names:      BTreeSet<String>,
+}
+impl Allowlist {
+    /// Names separated by whitespace or newlines, `#` starts a comment.
+    pub fn parse(contents: &str) -> Result<Self> {
+        let mut names = BTreeSet::new();
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            for name in line.split_whitespace() {
+                if !is_node_name(name) {
+                    return Err(Error::new(ErrorKind::InvalidData, format!("Invalid node name: {}", name)));
+                }
+                names.insert(name.to_string());
+            }
+        }
+        Ok(Allowlist { names })
+    }
+
+    /// The nodes with a host file in `dir`, symlinks followed. Scripts like
+    /// `<name>-up` are not node names and left out.
+    pub fn from_hosts(dir: &Path) -> Result<Self> {
+        let mut names = BTreeSet::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if is_node_name(&name) && fs::metadata(entry.path())?.is_file() {
+                names.insert(name);
+            }
+        }
+        Ok(Allowlist { names })
+    }
+
+    /// A `hosts/` directory or a file listing the names.
+    pub fn load(path: &Path) -> Result<Self> {
+        if path.is_dir() {
+            Self::from_hosts(path)
+        } else {
+            Self::parse(&fs::read_to_string(path)?)
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VanishedNode {
+    pub name:       String,
+    /// When tincd last listed the node.
+    pub last_seen:  i64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AllowlistReport {
+    /// Where the names come from, none if there is no allowlist.
+    pub source:      Option<String>,
+    pub expected:    usize,
+    /// Nodes in the mesh that are not on the list.
+    pub unknown:     Vec<String>,
+    /// Listed nodes not reachable once since tincmgr started.
+    pub never_seen:  Vec<String>,
+    /// Listed nodes tincd still knows that were reachable but no longer are.
+    pub unreachable: Vec<String>,
+    /// Listed nodes that were reachable and dropped out of tincd's node list.
+    pub vanished:    Vec<VanishedNode>,
+}
+
+/// Compares the nodes tincd knows with the allowlist every poll. The list
+/// is read again each time, so new host files are picked up.
+pub struct AllowlistMonitor {
+    source:         Option<PathBuf>,
+    allowlist:      Allowlist,
+    /// When each node was last in the node dump.
+    last_listed:    BTreeMap<String, i64>,
+    reached:        BTreeSet<String>,
+    unknown:        BTreeSet<String>,
+}
+impl AllowlistMonitor {
+    pub fn new(source: Option<PathBuf>) -> Self {
+        AllowlistMonitor {
+            source,
+            allowlist: Allowlist::default(),
+            last_listed: BTreeMap::new(),
+            reached: BTreeSet::new(),
+            unknown: BTreeSet::new(),
+        }
+    }
+
+    pub fn update(&mut self, data: &Data, time: i64) -> (AllowlistReport, Vec<Event>) {
+        let source = match self.source {
+            Some(ref source) => source,
+            None => return (AllowlistReport::default(), vec![]),
+        };
+        match Allowlist::load(source) {
+            Ok(allowlist) => self.allowlist = allowlist,
+            Err(e) => warn!("Read allowlist {} failed, keeping the previous one: {:?}", source.display(), e),
+        }
+
+        let mut events = vec![];
+        let mut unknown = BTreeSet::new();
+        for node in data.nodes.iter() {
+            self.last_listed.insert(node.name.clone(), time);
+            if node.local || node.reachable == 1 {
+                self.reached.insert(node.name.clone());
+            }
+            // An empty list is a missing one, not a mesh of strangers.
+            if node.local || self.allowlist.names.is_empty() || self.allowlist.names.contains(&node.name) {
+                continue;
+            }
+            if !self.unknown.contains(&node.name) {
+                warn!("Unknown node {} in the mesh", node.name);
+                events.push(Event {
+                    time,
+                    node: node.name.clone(),
+                    kind: EventKind::UnknownNode { reachable: node.reachable == 1 },
+                });
+            }
+            unknown.insert(node.name.clone());
+        }
+        self.unknown = unknown;
+
+        let mut report = AllowlistReport {
+            source: Some(source.display().to_string()),
+            expected: self.allowlist.names.len(),
+            unknown: self.unknown.iter().cloned().collect(),
+            ..AllowlistReport::default()
+        };
+        for name in self.allowlist.names.iter() {
+            let node = data.nodes.iter().find(|x| &x.name == name);
+            match node {
+                _ if !self.reached.contains(name) => report.never_seen.push(name.clone()),
+                Some(node) if node.local || node.reachable == 1 => (),
+                Some(_) => report.unreachable.push(name.clone()),
+                None => report.vanished.push(VanishedNode { name: name.clone(), last_seen: self.last_listed[name] }),
+            }
+        }
+        (report, events)
+    }
+}
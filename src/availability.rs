@@ -0,0 +1,136 @@
+use chrono::{Local, TimeZone};
+
+use crate::tsdb::{Point, Resolution, Tsdb};
+
+/// Raw polls further apart than this mean tincmgr was not watching, and
+/// the time in between is not counted.
+const MAX_RAW_GAP: i64 = 300;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Availability {
+    pub subject:        String,
+    /// Seconds covered by recorded polls.
+    pub observed:       i64,
+    pub downtime:       i64,
+    /// Percent of the observed time the subject was reachable.
+    pub availability:   Option<f64>,
+    pub outages:        usize,
+    pub longest_outage: i64,
+    /// Mean time to repair, the mean outage length in seconds.
+    pub mttr:           Option<i64>,
+}
+impl Availability {
+    fn from(subject: &str, resolution: Resolution, points: &[Point], to: i64) -> Self {
+        let mut observed = 0.0;
+        let mut downtime = 0.0;
+        let mut outages = vec![];
+        let mut outage: Option<f64> = None;
+        for (i, point) in points.iter().enumerate() {
+            let end = match resolution {
+                Resolution::Raw => match points.get(i + 1) {
+                    Some(next) if next.time - point.time <= MAX_RAW_GAP => next.time,
+                    _ => point.time,
+                },
+                _ => point.time + resolution.step(),
+            };
+            let duration = (end.min(to) - point.time).max(0) as f64;
+            let down = duration * (1.0 - point.value);
+            observed += duration;
+            downtime += down;
+            if point.value < 1.0 {
+                outage = Some(outage.unwrap_or(0.0) + down);
+            } else if let Some(length) = outage.take() {
+                outages.push(length);
+            }
+        }
+        outages.extend(outage);
+
+        let total: f64 = outages.iter().sum();
+        Availability {
+            subject: subject.to_string(),
+            observed: observed.round() as i64,
+            downtime: downtime.round() as i64,
+            availability: if observed > 0.0 { Some(100.0 * (observed - downtime) / observed) } else { None },
+            outages: outages.len(),
+            longest_outage: outages.iter().cloned().fold(0.0, f64::max).round() as i64,
+            mttr: if outages.is_empty() { None } else { Some((total / outages.len() as f64).round() as i64) },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AvailabilityReport {
+    pub from:       i64,
+    pub to:         i64,
+    pub nodes:      Vec<Availability>,
+    pub links:      Vec<Availability>,
+}
+impl AvailabilityReport {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("kind,subject,availability,observed,downtime,outages,longest_outage,mttr\n");
+        let rows = self.nodes.iter().map(|x| ("node", x)).chain(self.links.iter().map(|x| ("link", x)));
+        for (kind, x) in rows {
+            csv += &format!("{},{},{},{},{},{},{},{}\n", kind, x.subject,
+                            x.availability.map(|x| format!("{:.3}", x)).unwrap_or_default(),
+                            x.observed, x.downtime, x.outages, x.longest_outage,
+                            x.mttr.map(|x| x.to_string()).unwrap_or_default());
+        }
+        csv
+    }
+
+    pub fn to_html(&self) -> String {
+        let time = |x: i64| Local.timestamp_opt(x, 0).single()
+            .map(|x| x.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let mut html = format!("<html>\n<head><title>VPN availability</title></head>\n<body>\n\
+                                <h1>VPN availability {} to {}</h1>\n", time(self.from), time(self.to));
+        for (title, rows) in [("Nodes", &self.nodes), ("Links", &self.links)].iter() {
+            html += &format!("<h2>{}</h2>\n<table border=\"1\">\n<tr><th>Name</th><th>Availability</th>\
+                              <th>Observed</th><th>Downtime</th><th>Outages</th><th>Longest outage</th>\
+                              <th>MTTR</th></tr>\n", title);
+            for x in rows.iter() {
+                html += &format!("<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                                 x.subject,
+                                 x.availability.map(|x| format!("{:.3}%", x)).unwrap_or_else(|| "-".to_string()),
+                                 duration(x.observed), duration(x.downtime), x.outages,
+                                 duration(x.longest_outage),
+                                 x.mttr.map(duration).unwrap_or_else(|| "-".to_string()));
+            }
+            html += "</table>\n";
+        }
+        html + "</body>\n</html>\n"
+    }
+}
+
+/// Availability of every node and link between `from` and `to`, from the
+/// reachability the polls recorded.
+pub fn report(tsdb: &Tsdb, from: i64, to: i64) -> AvailabilityReport {
+    let availability = |metric: &str| -> Vec<Availability> {
+        tsdb.series().iter()
+            .filter(|id| id.metric == metric)
+            .filter_map(|id| tsdb.query(id, from, to, None))
+            .map(|series| Availability::from(&series.id.subject, series.resolution, &series.points, to))
+            .collect()
+    };
+    AvailabilityReport {
+        from,
+        to,
+        nodes: availability("node_reachable"),
+        links: availability("link_reachable"),
+    }
+}
+
+/// Start and end of a calendar month given as `YYYY-MM`, in local time.
+pub fn month(month: &str) -> Option<(i64, i64)> {
+    let mut iter = month.splitn(2, '-');
+    let year: i32 = iter.next()?.parse().ok()?;
+    let month: u32 = iter.next()?.parse().ok()?;
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    let start = Local.with_ymd_and_hms(year, month, 1, 0, 0, 0).earliest()?;
+    let end = Local.with_ymd_and_hms(next_year, next_month, 1, 0, 0, 0).earliest()?;
+    Some((start.timestamp(), end.timestamp()))
+}
+
+fn duration(seconds: i64) -> String {
+    format!("{}h {:02}m {:02}s", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
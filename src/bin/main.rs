@@ -10,12 +10,33 @@ extern crate log;
 
 extern crate serde;
 extern crate serde_json;
+extern crate chrono;
 
 extern crate tincmgr;
 use tincmgr::logging::init_logger;
 use tincmgr::tinc_tcp_stream::TincStream;
 use tincmgr::domain::Data;
+use tincmgr::domain::audit;
+use tincmgr::domain::conflicts;
+use tincmgr::domain::lookup;
+use tincmgr::domain::subnets::Net;
+use tincmgr::domain::trace;
 use tincmgr::web_server::web_server;
+use tincmgr::purge::{PurgePolicy, PurgeReport, Purger};
+use tincmgr::state::{SharedState, State};
+use tincmgr::pidfile::{self, PidFile};
+use tincmgr::process::ProcessStats;
+use tincmgr::interface::{self, InterfaceStats};
+use tincmgr::tinc_conf;
+use tincmgr::routes::{self, RouteReport};
+use tincmgr::pmtu::PmtuMonitor;
+use tincmgr::endpoints::EndpointTracker;
+use tincmgr::flaps::{self, FlapDetector};
+use tincmgr::latency::{self, LatencyMonitor};
+use tincmgr::tsdb::{self, Tsdb};
+use tincmgr::accounting::{self, TrafficAccounting};
+use tincmgr::allowlist::{Allowlist, AllowlistMonitor};
+use tincmgr::quotas::{QuotaConfig, QuotaMonitor};
 
 use std::env;
 use std::thread::sleep;
@@ -23,11 +44,16 @@ use std::thread::spawn;
 use std::time::Duration;
 use std::path::{PathBuf, Path};
 use std::fs;
+use std::sync::{Arc, RwLock};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
 
 const DEFAULT_PIDFILE_PATH: &str = "/root/tinc/tinc.pid";
 const DEFAULT_LOG_DIR: &str = "/var/log/tincmgr";
 const DEFAULT_LOG_FILE: &str = "/tincmgr.log";
 const DEFAULT_WEB_SERVER_PORT: &str = "8080";
+const DEFAULT_PURGE_POLICY: &str = "never";
+const DEFAULT_MIN_PMTU: &str = "1280";
+const POLL_INTERVAL_MS: u64 = 20000;
 
 error_chain! {
     errors {
@@ -47,6 +73,18 @@ error_chain! {
             description("Error find tinc pid file")
             display("{}", msg)
         }
+        InvalidArgument(msg: String) {
+            description("Invalid command line argument")
+            display("{}", msg)
+        }
+        LookupError(msg: String) {
+            description("No subnet contains the address")
+            display("{}", msg)
+        }
+        TraceError(msg: String) {
+            description("Can not trace the route")
+            display("{}", msg)
+        }
     }
 }
 
@@ -105,6 +143,135 @@ fn run() -> Result<()> {
                     ),
                 )
         )
+        .arg(
+            clap::Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .takes_value(true)
+                .value_name("dir")
+                .help("Tinc configuration directory.\ndefualt: /etc/tinc/NETNAME"),
+        )
+        .arg(
+            clap::Arg::with_name("net")
+                .short("n")
+                .long("net")
+                .takes_value(true)
+                .value_name("netname")
+                .help("Connect to net NETNAME.\ndefualt: from a pid file named tinc.NETNAME.pid"),
+        )
+        .arg(
+            clap::Arg::with_name("allowlist")
+                .long("allowlist")
+                .takes_value(true)
+                .value_name("path")
+                .help("File listing the expected node names, or a hosts directory.\ndefualt: the hosts directory of the config directory"),
+        )
+        .arg(
+            clap::Arg::with_name("interface")
+                .short("i")
+                .long("interface")
+                .takes_value(true)
+                .value_name("name")
+                .help("VPN interface to monitor.\ndefualt: Interface from tinc.conf, or the netname"),
+        )
+        .arg(
+            clap::Arg::with_name("purge")
+                .long("purge")
+                .takes_value(true)
+                .value_name("seconds")
+                .help(
+                    &format!(
+                        "Purge unreachable nodes every SECONDS, or never.\ndefualt:{}",
+                        DEFAULT_PURGE_POLICY,
+                    ),
+                )
+        )
+        .arg(
+            clap::Arg::with_name("min-pmtu")
+                .long("min-pmtu")
+                .takes_value(true)
+                .value_name("bytes")
+                .help(
+                    &format!(
+                        "Warn about nodes whose path MTU is below BYTES.\ndefualt:{}",
+                        DEFAULT_MIN_PMTU,
+                    ),
+                )
+        )
+        .arg(
+            clap::Arg::with_name("flap-window")
+                .long("flap-window")
+                .takes_value(true)
+                .value_name("seconds")
+                .help(
+                    &format!(
+                        "Window to count node and link changes in.\ndefualt:{}",
+                        flaps::DEFAULT_FLAP_WINDOW,
+                    ),
+                )
+        )
+        .arg(
+            clap::Arg::with_name("flap-threshold")
+                .long("flap-threshold")
+                .takes_value(true)
+                .value_name("changes")
+                .help(
+                    &format!(
+                        "Changes within the flap window that make a node or link flapping.\ndefualt:{}",
+                        flaps::DEFAULT_FLAP_THRESHOLD,
+                    ),
+                )
+        )
+        .arg(
+            clap::Arg::with_name("latency-windows")
+                .long("latency-windows")
+                .takes_value(true)
+                .value_name("windows")
+                .help(
+                    &format!(
+                        "Windows to summarize link latency over, like 30s,5m,1h,1d.\ndefualt:{}",
+                        latency::DEFAULT_WINDOWS,
+                    ),
+                )
+        )
+        .arg(
+            clap::Arg::with_name("quotas")
+                .long("quotas")
+                .takes_value(true)
+                .value_name("file")
+                .help("Per node traffic quotas and rate limits to warn about."),
+        )
+        .arg(
+            clap::Arg::with_name("tsdb")
+                .long("tsdb")
+                .takes_value(true)
+                .value_name("dir")
+                .help(
+                    &format!(
+                        "Directory to keep the metric history in.\ndefualt:{}",
+                        tsdb::DEFAULT_TSDB_DIR,
+                    ),
+                )
+        )
+        .arg(
+            clap::Arg::with_name("purge-dry-run")
+                .long("purge-dry-run")
+                .help("Print the nodes, subnets and edges a purge would remove, then exit."),
+        )
+        .arg(
+            clap::Arg::with_name("lookup")
+                .long("lookup")
+                .takes_value(true)
+                .value_name("address")
+                .help("Print the node owning the IP or MAC ADDRESS, then exit."),
+        )
+        .arg(
+            clap::Arg::with_name("trace")
+                .long("trace")
+                .takes_value(true)
+                .value_name("dest")
+                .help("Print the route to a node, or the node owning an address, then exit."),
+        )
         .get_matches();
 
     let log_level = match app.value_of("debug") {
@@ -132,6 +299,52 @@ fn run() -> Result<()> {
         None => DEFAULT_WEB_SERVER_PORT,
     };
 
+    let purge = app.value_of("purge").unwrap_or(DEFAULT_PURGE_POLICY);
+    let purge_policy = PurgePolicy::parse(purge)
+        .ok_or_else(|| ErrorKind::InvalidArgument(format!("Invalid purge policy: {}", purge)))?;
+
+    let min_pmtu = app.value_of("min-pmtu").unwrap_or(DEFAULT_MIN_PMTU);
+    let min_pmtu: u16 = min_pmtu.parse()
+        .map_err(|_| ErrorKind::InvalidArgument(format!("Invalid minimum PMTU: {}", min_pmtu)))?;
+
+    let latency_windows = app.value_of("latency-windows").unwrap_or(latency::DEFAULT_WINDOWS);
+    let latency_windows = latency::parse_windows(latency_windows)
+        .ok_or_else(|| ErrorKind::InvalidArgument(format!("Invalid latency windows: {}", latency_windows)))?;
+
+    let flap_window = app.value_of("flap-window").unwrap_or(flaps::DEFAULT_FLAP_WINDOW);
+    let flap_window: i64 = flap_window.parse().ok().filter(|x| *x > 0)
+        .ok_or_else(|| ErrorKind::InvalidArgument(format!("Invalid flap window: {}", flap_window)))?;
+    let flap_threshold = app.value_of("flap-threshold").unwrap_or(flaps::DEFAULT_FLAP_THRESHOLD);
+    let flap_threshold: usize = flap_threshold.parse().ok().filter(|x| *x > 1)
+        .ok_or_else(|| ErrorKind::InvalidArgument(format!("Invalid flap threshold: {}", flap_threshold)))?;
+
+    let quotas = match app.value_of("quotas") {
+        Some(path) => QuotaConfig::read(Path::new(path))
+            .map_err(|e| ErrorKind::InvalidArgument(format!("Invalid quotas {}: {}", path, e)))?,
+        None => QuotaConfig::default(),
+    };
+
+    let netname = match app.value_of("net") {
+        Some(net) => Some(net.to_string()),
+        None => tinc_conf::netname(Path::new(pidfile)),
+    };
+    let confdir = match app.value_of("config") {
+        Some(confdir) => PathBuf::from(confdir),
+        None => tinc_conf::confdir(netname.as_deref()),
+    };
+    let allowlist = match app.value_of("allowlist") {
+        Some(path) => {
+            Allowlist::load(Path::new(path))
+                .map_err(|e| ErrorKind::InvalidArgument(format!("Invalid allowlist {}: {}", path, e)))?;
+            Some(PathBuf::from(path))
+        }
+        None => Some(confdir.join("hosts")).filter(|x| x.is_dir()),
+    };
+    let interface = match app.value_of("interface") {
+        Some(interface) => Some(interface.to_string()),
+        None => interface::interface_name(&confdir, netname.as_deref()),
+    };
+
     init_logger(
         log_level,
         Some(&PathBuf::from(DEFAULT_LOG_DIR)),
@@ -139,56 +352,257 @@ fn run() -> Result<()> {
         true,
     ).chain_err(|| ErrorKind::LogError("Unable to initialize logger"))?;
 
+    if app.is_present("purge-dry-run") {
+        let data = get_data(pidfile)?;
+        let report = PurgeReport::from(&data);
+        println!("{}", serde_json::to_string_pretty(&report)
+            .chain_err(|| ErrorKind::WriteJsonError("when print purge report"))?);
+        return Ok(());
+    }
+
+    if let Some(addr) = app.value_of("lookup") {
+        let address = Net::parse_address(addr)
+            .ok_or_else(|| ErrorKind::InvalidArgument(format!("Invalid address: {}", addr)))?;
+        let data = get_data(pidfile)?;
+        let lookup = lookup::lookup(&data, &address)
+            .ok_or_else(|| ErrorKind::LookupError(format!("No subnet contains {}", addr)))?;
+        println!("{}", serde_json::to_string_pretty(&lookup)
+            .chain_err(|| ErrorKind::WriteJsonError("when print lookup"))?);
+        return Ok(());
+    }
+
+    if let Some(destination) = app.value_of("trace") {
+        let data = get_data(pidfile)?;
+        let trace = trace::trace(&data, destination)
+            .ok_or_else(|| ErrorKind::TraceError(format!("Unknown destination {}", destination)))?;
+        println!("{}", serde_json::to_string_pretty(&trace)
+            .chain_err(|| ErrorKind::WriteJsonError("when print trace"))?);
+        return Ok(());
+    }
+
     let port_str = port.to_string();
+    let tsdb_dir = app.value_of("tsdb").unwrap_or(tsdb::DEFAULT_TSDB_DIR);
+    let series = match Tsdb::open(Path::new(tsdb_dir)) {
+        Ok(series) => series,
+        Err(e) => {
+            warn!("Open metric history {} failed, keeping it in memory only: {:?}", tsdb_dir, e);
+            Tsdb::default()
+        }
+    };
+    let traffic_file = Path::new(tsdb_dir).join(accounting::TRAFFIC_FILE);
+    let traffic = match TrafficAccounting::open(&traffic_file) {
+        Ok(traffic) => traffic,
+        Err(e) => {
+            warn!("Load traffic totals {} failed, starting over: {:?}", traffic_file.display(), e);
+            TrafficAccounting::default()
+        }
+    };
+    let state: SharedState = Arc::new(RwLock::new(State { series, traffic, ..State::default() }));
 
     let data_dir = env::current_dir().unwrap().to_str().unwrap().to_owned();
     let data_dir = data_dir.to_string() + "/www/";
     let data_dir_clone1 = data_dir.clone();
 
-    let handle_web_server = spawn(move || web_server(&port_str, &data_dir_clone1));
+    let web_state = state.clone();
+    let handle_web_server = spawn(move || web_server(&port_str, &data_dir_clone1, web_state));
 
-    let data_dir_clone2 = data_dir.clone();
-    let pidfile = pidfile.to_string();
-    let handle_main_loop = spawn(move || main_loop(&pidfile, &data_dir_clone2));
+    let settings = Settings {
+        pidfile: pidfile.to_string(),
+        data_dir: data_dir.clone(),
+        purge_policy,
+        interface,
+        min_pmtu,
+        latency_windows,
+        flap_window,
+        flap_threshold,
+        quotas,
+        allowlist,
+    };
+    let handle_main_loop = spawn(move || main_loop(&settings, state));
 
-    handle_main_loop.join().unwrap();
-    handle_web_server.join().unwrap();
+    let _ = handle_main_loop.join().unwrap();
+    let _ = handle_web_server.join().unwrap();
 
     Ok(())
 }
 
+struct Settings {
+    pidfile:        String,
+    data_dir:       String,
+    purge_policy:   PurgePolicy,
+    interface:      Option<String>,
+    min_pmtu:       u16,
+    latency_windows: Vec<i64>,
+    flap_window:    i64,
+    flap_threshold: usize,
+    quotas:         QuotaConfig,
+    allowlist:      Option<PathBuf>,
+}
+
 fn main_loop(
-    pidfile:     &str,
-    data_dir:    &str,
+    settings:       &Settings,
+    state:          SharedState,
 ) -> Result<()> {
-    let data_file = data_dir.to_string() + "data/nodes.json";
+    let pidfile = settings.pidfile.as_str();
+    let data_file = settings.data_dir.to_string() + "data/nodes.json";
+    let mut purger = Purger::new(settings.purge_policy);
+    let mut pmtu_monitor = PmtuMonitor::new(settings.min_pmtu);
+    let mut endpoint_tracker = EndpointTracker::new();
+    let mut flap_detector = FlapDetector::new(settings.flap_window, settings.flap_threshold);
+    let mut quota_monitor = QuotaMonitor::new(settings.quotas.clone());
+    let mut latency_monitor = LatencyMonitor::new(settings.latency_windows.clone());
+    let mut allowlist_monitor = AllowlistMonitor::new(settings.allowlist.clone());
+    let pidfile_changes = match pidfile::watch(pidfile) {
+        Ok(changes) => Some(changes),
+        Err(e) => {
+            warn!("Can not watch pid file {}: {:?}", pidfile, e);
+            None
+        }
+    };
     loop {
         debug!("Start fresh.");
-        let data = match get_data(pidfile) {
+        let mut data = match get_data(pidfile) {
             Ok(data) => data,
             Err(e) => {
-                error!("{:?}", e);
-                std::process::exit(1);
+                error!("{}", e.display_chain());
+                let cause = e.iter().map(|e| e.to_string()).collect::<Vec<String>>().join(": ");
+                state.write().unwrap().attach_error = Some(cause);
+                wait_next_poll(&pidfile_changes);
+                continue;
             }
         };
-        let _ = match write_json(&data_file, data) {
-            Ok(data) => data,
+        if let Some(ref name) = settings.interface {
+            let now = chrono::Local::now().timestamp();
+            match InterfaceStats::read(Path::new(interface::SYSFS_NET), name, now) {
+                Ok(sample) => {
+                    let mut state = state.write().unwrap();
+                    data.interface = Some(state.interface.push(sample).clone());
+                }
+                Err(e) => debug!("Read interface {} statistics failed: {:?}", name, e),
+            }
+        }
+        let route_report = match settings.interface {
+            Some(ref name) => match routes::read_kernel_routes(Path::new(routes::PROC_NET)) {
+                Ok(kernel_routes) => routes::check_routes(&data, name, &kernel_routes),
+                Err(e) => {
+                    warn!("Read kernel routes failed: {:?}", e);
+                    RouteReport::default()
+                }
+            },
+            None => RouteReport::default(),
+        };
+        for problem in route_report.problems.iter() {
+            debug!("Route problem: {:?}", problem);
+        }
+        let now = chrono::Local::now().timestamp();
+        let pmtu_report = pmtu_monitor.update(&data, now);
+        let (endpoint_report, mut events) = endpoint_tracker.update(&data, now);
+        let (flap_report, flap_events) = flap_detector.update(&data, now);
+        events.extend(flap_events);
+        let (allowlist_report, allowlist_events) = allowlist_monitor.update(&data, now);
+        events.extend(allowlist_events);
+        let latency_report = latency_monitor.update(&data, now);
+        let conflict_report = conflicts::check_conflicts(&data.subnets);
+        for conflict in conflict_report.conflicts.iter() {
+            debug!("Subnet conflict {:?}: {} of {:?} and {} of {:?}", conflict.kind,
+                  conflict.subnet, conflict.subnet.owner, conflict.other, conflict.other.owner);
+        }
+        let audit_report = audit::audit(&data);
+        for finding in audit_report.findings.iter() {
+            debug!("Audit {:?} of {}: {:?}", finding.severity, finding.node, finding.kind);
+        }
+        let data_str = match serde_json::to_string(&data) {
+            Ok(data_str) => data_str,
             Err(e) => {
                 error!("{:?}", e);
                 std::process::exit(1);
             }
         };
+        if let Err(e) = write_json(&data_file, data_str) {
+            error!("{:?}", e);
+            std::process::exit(1);
+        }
+        let purge = match purger.run(pidfile, &data) {
+            Ok(purge) => Some(purge),
+            Err(e) => {
+                error!("Purge failed: {:?}", e);
+                None
+            }
+        };
+        let previous = state.read().unwrap().process.clone();
+        let process = match PidFile::read(pidfile)
+            .and_then(|x| ProcessStats::sample(x.pid, previous.as_ref())) {
+            Ok(process) => Some(process),
+            Err(e) => {
+                warn!("Sample tincd process failed: {:?}", e);
+                None
+            }
+        };
+        let (series_write, traffic_write) = {
+            let mut state = state.write().unwrap();
+            let series_write = state.series.record(now, &tsdb::samples(&data));
+            let traffic_write = match state.traffic.update(&data, now) {
+                Ok(traffic_write) => traffic_write,
+                Err(e) => {
+                    warn!("Save traffic totals failed: {:?}", e);
+                    None
+                }
+            };
+            let (quota_report, quota_events) = quota_monitor.update(&data, &state.traffic, now);
+            state.quotas = quota_report;
+            events.extend(quota_events);
+            state.data = Some(data);
+            state.process = process;
+            state.routes = route_report;
+            state.conflicts = conflict_report;
+            state.audit = audit_report;
+            state.pmtu = pmtu_report;
+            state.endpoints = endpoint_report;
+            state.latency = latency_report;
+            state.flaps = flap_report;
+            state.allowlist = allowlist_report;
+            for event in events {
+                state.events.push(event);
+            }
+            state.attach_error = None;
+            if let Some(purge) = purge {
+                state.purge = purge;
+            }
+            (series_write, traffic_write)
+        };
+        // Keep the disk out of the state lock, the web server waits on it.
+        if let Err(e) = series_write.save() {
+            warn!("Record metric history failed: {:?}", e);
+        }
+        if let Some(Err(e)) = traffic_write.map(|x| x.save()) {
+            warn!("Save traffic totals failed: {:?}", e);
+        }
         debug!("Finnish fresh.");
-        sleep(Duration::from_millis(20000));
+        wait_next_poll(&pidfile_changes);
     }
 }
 
-fn get_data(pid_path: &str) -> Result<String> {
-    let mut _data_str = String::new();
+// Sleep until the next poll, or until tincd rewrites its pid file after a
+// restart so we reconnect with the new cookie straight away.
+fn wait_next_poll(pidfile_changes: &Option<Receiver<()>>) {
+    let interval = Duration::from_millis(POLL_INTERVAL_MS);
+    match pidfile_changes {
+        Some(changes) => match changes.recv_timeout(interval) {
+            Ok(()) => {
+                debug!("Pid file changed, reconnect.");
+                while changes.try_recv().is_ok() {}
+            }
+            Err(RecvTimeoutError::Timeout) => (),
+            Err(RecvTimeoutError::Disconnected) => sleep(interval),
+        },
+        None => sleep(interval),
+    }
+}
 
+fn get_data(pid_path: &str) -> Result<Data> {
     loop {
         let mut tinc_stream = TincStream::new(pid_path)
-            .chain_err(|| ErrorKind::NotFindTincPiDError("Not find tinc pid file, Run tincd first."))?;
+            .chain_err(|| ErrorKind::NotFindTincPiDError("Can not attach to tincd, Run tincd first."))?;
 
         if let Ok(nodes) = tinc_stream.dump_nodes() {
             debug!("dump_nodes.");
@@ -196,23 +610,25 @@ fn get_data(pid_path: &str) -> Result<String> {
                 debug!("dump_edges.");
                 if let Ok(subnets) = tinc_stream.dump_subnets() {
                     debug!("dump_subnets.");
-                    let data = Data::new(
+                    let mut data = Data::new(
                         nodes,
                         subnets,
                         edges);
-                    if let Ok(data) = serde_json::to_string(&data) {
-                        _data_str = data;
-                        if let Ok(_) = tinc_stream.purge() {
-                            ()
-                        }
-                        break;
+                    data.set_daemon(tinc_stream.identity().clone());
+                    match tinc_stream.dump_connections() {
+                        Ok(connections) => data.set_connections(&connections),
+                        Err(e) => warn!("Dump connections failed: {:?}", e),
+                    }
+                    match tinc_stream.dump_traffic() {
+                        Ok(traffic) => data.set_traffic(&traffic),
+                        Err(e) => warn!("Dump traffic failed: {:?}", e),
                     }
+                    return Ok(data);
                 }
             }
         }
         sleep(Duration::from_millis(1000));
     }
-    Ok(_data_str)
 }
 
 fn write_json(file_path: &str, data: String) -> Result<()> {
@@ -1,5 +1,6 @@
 use std::io::Result;
 
+use super::domain::connections::Connection;
 use super::tinc_tcp_stream::TincStream;
 
 pub fn stop(pid_path: &str) -> Result<()> {
@@ -34,10 +35,10 @@ pub fn dump_subnets(pid_path: &str) -> Result<()> {
 }
 
 
-pub fn dump_connections(pid_path: &str) -> Result<()> {
+pub fn dump_connections(pid_path: &str) -> Result<Vec<Connection>> {
     let mut tinc_stream = TincStream::new(pid_path)?;
-    let _res = tinc_stream.dump_connections()?;
-    Ok(())
+    let connections = tinc_stream.dump_connections()?;
+    Ok(connections.iter().map(Connection::from).collect())
 }
 
 pub fn dump_graph(pid_path: &str) -> Result<()> {
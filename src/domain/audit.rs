@@ -0,0 +1,191 @@
+use crate::domain::Data;
+use crate::domain::nodes::Node;
+
+/// Shortest MAC, in bytes, not flagged. tinc 1.0 defaults to 4.
+const MIN_MACLENGTH: i32 = 8;
+
+/// OpenSSL NIDs of the ciphers legacy tinc can be configured with, and
+/// whether they are weak: 64 bit blocks, broken stream ciphers or ECB.
+const CIPHERS: &[(i32, &str, bool)] = &[
+    (5, "rc4", true),
+    (31, "des-cbc", true),
+    (37, "rc2-cbc", true),
+    (44, "des-ede3-cbc", true),
+    (91, "bf-cbc", true),
+    (92, "bf-ecb", true),
+    (93, "bf-cfb", true),
+    (94, "bf-ofb", true),
+    (108, "cast5-cbc", true),
+    (418, "aes-128-ecb", true),
+    (419, "aes-128-cbc", false),
+    (420, "aes-128-ofb", false),
+    (421, "aes-128-cfb", false),
+    (422, "aes-192-ecb", true),
+    (423, "aes-192-cbc", false),
+    (424, "aes-192-ofb", false),
+    (425, "aes-192-cfb", false),
+    (426, "aes-256-ecb", true),
+    (427, "aes-256-cbc", false),
+    (428, "aes-256-ofb", false),
+    (429, "aes-256-cfb", false),
+    (751, "camellia-128-cbc", false),
+    (753, "camellia-256-cbc", false),
+    (895, "aes-128-gcm", false),
+    (901, "aes-256-gcm", false),
+    (904, "aes-128-ctr", false),
+    (906, "aes-256-ctr", false),
+    (1018, "chacha20-poly1305", false),
+];
+
+/// OpenSSL NIDs of message digests, and whether they are weak.
+const DIGESTS: &[(i32, &str, bool)] = &[
+    (4, "md5", true),
+    (64, "sha1", true),
+    (117, "ripemd160", false),
+    (257, "md4", true),
+    (672, "sha256", false),
+    (673, "sha384", false),
+    (674, "sha512", false),
+    (675, "sha224", false),
+    (804, "whirlpool", false),
+];
+
+pub fn cipher_name(nid: i32) -> String {
+    name(CIPHERS, nid)
+}
+
+pub fn digest_name(nid: i32) -> String {
+    name(DIGESTS, nid)
+}
+
+fn name(table: &[(i32, &str, bool)], nid: i32) -> String {
+    match table.iter().find(|x| x.0 == nid) {
+        Some(x) => x.1.to_string(),
+        None if nid == 0 => "none".to_string(),
+        None => format!("nid {}", nid),
+    }
+}
+
+fn is_weak(table: &[(i32, &str, bool)], nid: i32) -> bool {
+    table.iter().any(|x| x.0 == nid && x.2)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Warning,
+    Critical,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FindingKind {
+    /// The node talks the tinc 1.0 protocol instead of SPTPS.
+    LegacyProtocol,
+    /// No key exchange with the node yet, its cipher and digest are unknown.
+    KeyNotNegotiated,
+    /// Packets to the node are not encrypted.
+    NoEncryption,
+    WeakCipher { cipher: String },
+    /// Packets to the node are not authenticated.
+    NoDigest,
+    WeakDigest { digest: String },
+    ShortMac { length: i32 },
+}
+impl FindingKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            FindingKind::NoEncryption | FindingKind::NoDigest => Severity::Critical,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub node:       String,
+    pub severity:   Severity,
+    #[serde(flatten)]
+    pub kind:       FindingKind,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProtocolVersion {
+    pub protocol_minor: u8,
+    pub sptps:          bool,
+    pub nodes:          Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct AuditReport {
+    pub checked:    usize,
+    pub findings:   Vec<Finding>,
+    /// Reachable nodes grouped by the protocol version they announce.
+    pub versions:   Vec<ProtocolVersion>,
+}
+impl AuditReport {
+    pub fn count(&self, severity: Severity) -> usize {
+        self.findings.iter().filter(|x| x.severity == severity).count()
+    }
+}
+
+/// Whether packets to the node go over SPTPS, which ignores the legacy
+/// cipher, digest and MAC length settings. A peer advertising a newer minor
+/// version still talks the legacy protocol until tincd sets the status bit.
+pub fn uses_sptps(node: &Node) -> bool {
+    node.status.sptps
+}
+
+/// Check the crypto every other reachable node is talked to with. What
+/// unreachable nodes announce is stale and left out.
+pub fn audit(data: &Data) -> AuditReport {
+    let mut report = AuditReport::default();
+    for node in data.nodes.iter().filter(|x| !x.local && x.reachable == 1) {
+        report.checked += 1;
+        let sptps = uses_sptps(node);
+        match report.versions.iter_mut()
+            .find(|x| x.protocol_minor == node.options.protocol_minor && x.sptps == sptps) {
+            Some(version) => version.nodes.push(node.name.clone()),
+            None => report.versions.push(ProtocolVersion {
+                protocol_minor: node.options.protocol_minor,
+                sptps,
+                nodes: vec![node.name.clone()],
+            }),
+        }
+        if sptps {
+            continue;
+        }
+
+        let mut kinds = vec![FindingKind::LegacyProtocol];
+        // tincd reports the cipher and digest of the last key exchange, which
+        // only runs when there is traffic for the node.
+        if !node.status.validkey {
+            kinds.push(FindingKind::KeyNotNegotiated);
+        } else {
+            if node.cipher == 0 {
+                kinds.push(FindingKind::NoEncryption);
+            } else if is_weak(CIPHERS, node.cipher) {
+                kinds.push(FindingKind::WeakCipher { cipher: cipher_name(node.cipher) });
+            }
+            if node.digest == 0 {
+                kinds.push(FindingKind::NoDigest);
+            } else {
+                if is_weak(DIGESTS, node.digest) {
+                    kinds.push(FindingKind::WeakDigest { digest: digest_name(node.digest) });
+                }
+                if node.maclength < MIN_MACLENGTH {
+                    kinds.push(FindingKind::ShortMac { length: node.maclength });
+                }
+            }
+        }
+        for kind in kinds {
+            report.findings.push(Finding {
+                node: node.name.clone(),
+                severity: kind.severity(),
+                kind,
+            });
+        }
+    }
+    report.versions.sort_by_key(|x| (x.protocol_minor, x.sptps));
+    report
+}
@@ -0,0 +1,68 @@
+use crate::domain::subnets::Subnet;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// Several nodes advertise the same net with different weights.
+    Duplicate,
+    /// Several nodes advertise the same net with the same weight, so the
+    /// owner tincd picks depends only on node name order.
+    WeightTie,
+    /// A more specific subnet of another node takes part of the subnet.
+    Shadowed,
+    /// The same node advertises nested subnets.
+    Overlap,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SubnetConflict {
+    pub kind:       ConflictKind,
+    pub subnet:     Subnet,
+    pub other:      Subnet,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ConflictReport {
+    pub checked:    usize,
+    pub conflicts:  Vec<SubnetConflict>,
+}
+impl ConflictReport {
+    pub fn count(&self, kind: &ConflictKind) -> usize {
+        self.conflicts.iter().filter(|x| &x.kind == kind).count()
+    }
+}
+
+/// Compare every pair of node subnets. Broadcast subnets belong to nobody
+/// and are left out.
+pub fn check_conflicts(subnets: &[Subnet]) -> ConflictReport {
+    let subnets: Vec<&Subnet> = subnets.iter().filter(|x| !x.is_broadcast()).collect();
+    let mut report = ConflictReport {
+        checked: subnets.len(),
+        ..ConflictReport::default()
+    };
+    for (i, a) in subnets.iter().enumerate() {
+        for b in subnets[i + 1..].iter() {
+            let same_owner = a.owner == b.owner;
+            let nested = a.net.contains(&b.net) || b.net.contains(&a.net);
+            let kind = if !nested || a.net == b.net && same_owner {
+                continue;
+            } else if a.net == b.net && a.weight == b.weight {
+                ConflictKind::WeightTie
+            } else if a.net == b.net {
+                ConflictKind::Duplicate
+            } else if same_owner {
+                ConflictKind::Overlap
+            } else {
+                ConflictKind::Shadowed
+            };
+            // The broader subnet is the one affected.
+            let (subnet, other) = if b.net.prefix() < a.net.prefix() { (b, a) } else { (a, b) };
+            report.conflicts.push(SubnetConflict {
+                kind,
+                subnet: (*subnet).clone(),
+                other: (*other).clone(),
+            });
+        }
+    }
+    report
+}
@@ -0,0 +1,73 @@
+use crate::domain::known;
+use crate::domain::options::Options;
+use crate::tinc_tcp_stream::SourceConnection;
+
+// Bits of connection_status_t in tinc's connection.h.
+const STATUS_PINGED: u32 = 1 << 0;
+const STATUS_ACTIVE: u32 = 1 << 1;
+const STATUS_CONNECTING: u32 = 1 << 2;
+const STATUS_ENCRYPTOUT: u32 = 1 << 6;
+const STATUS_DECRYPTIN: u32 = 1 << 7;
+const STATUS_MST: u32 = 1 << 8;
+const STATUS_CONTROL: u32 = 1 << 9;
+const STATUS_PCAP: u32 = 1 << 10;
+const STATUS_LOG: u32 = 1 << 11;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionStatus {
+    pub pinged:         bool,
+    /// Only set by tinc 1.0, 1.1 leaves the bit unused.
+    pub active:         bool,
+    pub connecting:     bool,
+    pub encryptout:     bool,
+    pub decryptin:      bool,
+    /// Part of the minimum spanning tree broadcasts follow.
+    pub mst:            bool,
+    pub control:        bool,
+    pub pcap:           bool,
+    pub log:            bool,
+}
+impl ConnectionStatus {
+    pub fn from_bits(status: u32) -> Self {
+        ConnectionStatus {
+            pinged: status & STATUS_PINGED != 0,
+            active: status & STATUS_ACTIVE != 0,
+            connecting: status & STATUS_CONNECTING != 0,
+            encryptout: status & STATUS_ENCRYPTOUT != 0,
+            decryptin: status & STATUS_DECRYPTIN != 0,
+            mst: status & STATUS_MST != 0,
+            control: status & STATUS_CONTROL != 0,
+            pcap: status & STATUS_PCAP != 0,
+            log: status & STATUS_LOG != 0,
+        }
+    }
+}
+
+/// A connection of the local daemon, from `dump_connections`: a meta
+/// connection to a peer, or a control connection like our own.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Connection {
+    pub name:       String,
+    pub address:    Option<String>,
+    pub port:       Option<u16>,
+    pub socket:     i32,
+    pub options:    Options,
+    pub status:     ConnectionStatus,
+}
+impl Connection {
+    pub fn from(source_connection: &SourceConnection) -> Self {
+        Connection {
+            name: source_connection.node.clone(),
+            address: known(&source_connection.host),
+            port: source_connection.port.parse().ok(),
+            socket: source_connection.socket.parse().unwrap_or(-1),
+            options: Options::from_bits(u32::from_str_radix(&source_connection.options, 16).unwrap_or(0)),
+            status: ConnectionStatus::from_bits(u32::from_str_radix(&source_connection.status_int, 16).unwrap_or(0)),
+        }
+    }
+
+    /// A meta connection to another node, rather than a control client.
+    pub fn is_meta(&self) -> bool {
+        !self.status.control
+    }
+}
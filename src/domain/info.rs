@@ -0,0 +1,26 @@
+use crate::domain::Data;
+use crate::domain::connections::Connection;
+use crate::domain::links::Link;
+use crate::domain::nodes::Node;
+
+/// Everything known about one node, like `tinc info <node>`. The node
+/// carries its subnets and traffic counters.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NodeInfo {
+    pub node:           Node,
+    pub links:          Vec<Link>,
+    pub connection:     Option<Connection>,
+}
+impl NodeInfo {
+    pub fn from(data: &Data, name: &str) -> Option<Self> {
+        let node = data.nodes.iter().find(|node| node.name == name)?;
+        Some(NodeInfo {
+            node: node.clone(),
+            links: data.links.iter()
+                .filter(|link| link.sname == name || link.tname == name)
+                .cloned()
+                .collect(),
+            connection: data.connections.iter().find(|x| x.name == name).cloned(),
+        })
+    }
+}
@@ -2,91 +2,157 @@ use std::collections::HashMap;
 
 use crate::tinc_tcp_stream::SourceEdge;
 use crate::domain::nodes::Node;
+use crate::domain::known;
 
+/// What we know about one end of a link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endpoint {
+    Reachable,
+    Unreachable,
+    /// The edge names a node that is missing from `dump_nodes`.
+    Unknown,
+}
+
+/// One direction of a link, as announced by its `from` node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct EdgeDirection {
+    pub weight:             u32,
+    pub address:            Option<String>,
+    pub port:               Option<u16>,
+    pub local_address:      Option<String>,
+    pub local_port:         Option<u16>,
+    pub options:            u32,
+    /// Average round trip time in milliseconds, if tincd measures it.
+    pub avg_rtt:            Option<u32>,
+}
+impl EdgeDirection {
+    fn from(source_edge: &SourceEdge) -> Self {
+        EdgeDirection {
+            weight:         source_edge.weight.parse().unwrap_or(1000),
+            address:        known(&source_edge.host),
+            port:           source_edge.port.parse().ok(),
+            local_address:  known(&source_edge.local_host),
+            local_port:     source_edge.local_port.parse().ok(),
+            options:        u32::from_str_radix(&source_edge.options, 16).unwrap_or(0),
+            avg_rtt:        source_edge.avg_rtt.as_ref().and_then(|x| x.parse().ok()),
+        }
+    }
+}
+
+/// An undirected pair of nodes. `forward` is the edge `sname -> tname`,
+/// `reverse` the edge `tname -> sname`; tincd normally announces both.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Link {
     pub sname:              String,
     pub tname:              String,
     pub frac:               f64,
-    pub target:             u32,
+    pub target:             Option<u32>,
     pub weight:             u32,
-    pub source:             u32,
+    pub avg_rtt:            Option<u32>,
+    pub source:             Option<u32>,
+    pub source_state:       Endpoint,
+    pub target_state:       Endpoint,
     pub reachable:          u8,
+    pub one_sided:          bool,
+    pub forward:            Option<EdgeDirection>,
+    pub reverse:            Option<EdgeDirection>,
     pub _hash:              String,
 }
 impl Link {
-    fn from(source_edge: SourceEdge, nodes: &HashMap<String, u32>) -> Option<Self> {
-        let weight = match source_edge.weight.parse() {
-            Ok(x) => x,
-            Err(_) => 1000,
-        };
-        let mut target = 0;
-        if nodes.contains_key(&source_edge.to) {
-            target = nodes[&source_edge.to];
-        }
-
-        let mut source = 0;
-        if nodes.contains_key(&source_edge.from) {
-            source = nodes[&source_edge.from];
-        }
-
-        let mut reachable = 0;
-        if source != 0 && target != 0 {
-            if source > target {
-                return None;
-            }
-            reachable = 1;
-        }
+    fn new(sname: &str, tname: &str, nodes: &HashMap<String, (u32, bool)>) -> Self {
+        let (source, source_state) = endpoint(sname, nodes);
+        let (target, target_state) = endpoint(tname, nodes);
+        let reachable = (source_state == Endpoint::Reachable
+            && target_state == Endpoint::Reachable) as u8;
 
-        let _hash = format!("{}-{}", source, target);
-        Some(Link {
-            sname:  source_edge.from,
-            tname:  source_edge.to,
+        Link {
+            sname:  sname.to_string(),
+            tname:  tname.to_string(),
             frac:   0.0,
             target,
-            weight,
+            weight: 0,
+            avg_rtt: None,
             source,
+            source_state,
+            target_state,
             reachable,
-            _hash,
-        })
+            one_sided: false,
+            forward: None,
+            reverse: None,
+            _hash:  format!("{}-{}", sname, tname),
+        }
     }
 
-    pub fn load_links(source_edges: Vec<SourceEdge>, nodes_info: &mut Vec<Node>) -> Vec<Link> {
-        let mut links: Vec<Link> = vec![];
+    /// Fill in the link weight and the one-sided flag once both directions
+    /// have been collected. The weight and RTT are the means of the known
+    /// directions.
+    fn finish(&mut self) {
+        let weights: Vec<u32> = self.directions().map(|d| d.weight).collect();
+        if !weights.is_empty() {
+            self.weight = weights.iter().sum::<u32>() / weights.len() as u32;
+        }
+        let rtts: Vec<u32> = self.directions().filter_map(|d| d.avg_rtt).collect();
+        if !rtts.is_empty() {
+            self.avg_rtt = Some(rtts.iter().sum::<u32>() / rtts.len() as u32);
+        }
+        self.one_sided = weights.len() < 2;
+    }
+
+    pub fn directions(&self) -> impl Iterator<Item = &EdgeDirection> {
+        self.forward.iter().chain(self.reverse.iter())
+    }
+
+    pub fn load_links(source_edges: Vec<SourceEdge>, nodes_info: &mut [Node]) -> Vec<Link> {
         let mut nodes = HashMap::new();
-        for node in nodes_info.clone() {
-            nodes.insert(node.name, node.index);
+        for node in nodes_info.iter() {
+            nodes.insert(node.name.clone(), (node.index, node.reachable == 1));
         }
+
+        let mut links: Vec<Link> = vec![];
+        let mut pairs: HashMap<(String, String), usize> = HashMap::new();
         for edge in source_edges {
-            if let Some(link) = Self::from(edge, &nodes) {
-                links.push(link);
+            let forward = edge.from <= edge.to;
+            let key = if forward {
+                (edge.from.clone(), edge.to.clone())
+            } else {
+                (edge.to.clone(), edge.from.clone())
+            };
+            let i = match pairs.get(&key) {
+                Some(i) => *i,
+                None => {
+                    links.push(Link::new(&key.0, &key.1, &nodes));
+                    pairs.insert(key, links.len() - 1);
+                    links.len() - 1
+                }
+            };
+            let direction = Some(EdgeDirection::from(&edge));
+            if forward {
+                links[i].forward = direction;
+            } else {
+                links[i].reverse = direction;
             }
         }
 
-        let mut links_hash = HashMap::new();
-        for link in &links {
-            if links_hash.contains_key(&link.sname) {
-                let mut num = links_hash[&link.sname];
-                num += 1;
-                links_hash.insert(link.sname.clone(), num);
-            }
-            else {
-                links_hash.insert(link.sname.clone(), 1);
-            }
-            if links_hash.contains_key(&link.tname) {
-                let mut num = links_hash[&link.tname];
-                num += 1;
-                links_hash.insert(link.tname.clone(), num);
-            }
-            else {
-                links_hash.insert(link.tname.clone(), 1);
-            }
+        let mut links_hash: HashMap<String, u32> = HashMap::new();
+        for link in links.iter_mut() {
+            link.finish();
+            *links_hash.entry(link.sname.clone()).or_insert(0) += 1;
+            *links_hash.entry(link.tname.clone()).or_insert(0) += 1;
         }
-        for mut node in nodes_info {
-            if links_hash.contains_key(&node.name) {
-                node.edges = links_hash[&node.name];
+        for node in nodes_info.iter_mut() {
+            if let Some(edges) = links_hash.get(&node.name) {
+                node.edges = *edges;
             }
         }
-        return links;
+        links
     }
-}
\ No newline at end of file
+}
+
+fn endpoint(name: &str, nodes: &HashMap<String, (u32, bool)>) -> (Option<u32>, Endpoint) {
+    match nodes.get(name) {
+        Some((index, true)) => (Some(*index), Endpoint::Reachable),
+        Some((index, false)) => (Some(*index), Endpoint::Unreachable),
+        None => (None, Endpoint::Unknown),
+    }
+}
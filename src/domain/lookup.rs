@@ -0,0 +1,48 @@
+use std::cmp::Reverse;
+
+use crate::domain::Data;
+use crate::domain::subnets::{Net, Subnet};
+
+/// Which node owns an address, like `tinc info <address>`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lookup {
+    pub address:    Net,
+    pub subnet:     Subnet,
+    /// `None` for the broadcast subnets.
+    pub owner:      Option<String>,
+    pub reachable:  bool,
+    pub nexthop:    Option<String>,
+    pub via:        Option<String>,
+    /// Every subnet containing the address, in the order tincd tries them.
+    pub matches:    Vec<Subnet>,
+}
+
+/// Longest prefix first, then lowest weight. Like tincd, take the first
+/// match whose owner is reachable, or the last match if none is.
+pub fn lookup(data: &Data, address: &Net) -> Option<Lookup> {
+    let mut matches: Vec<&Subnet> = data.subnets.iter()
+        .filter(|subnet| subnet.net.contains(address))
+        .collect();
+    matches.sort_by_key(|subnet| (Reverse(subnet.net.prefix()), subnet.weight));
+
+    let owner = |subnet: &Subnet| subnet.owner.as_ref()
+        .and_then(|owner| data.nodes.iter().find(|node| &node.name == owner));
+    let reachable = |subnet: &Subnet| match subnet.owner {
+        Some(_) => owner(subnet).map(|node| node.reachable != 0).unwrap_or(false),
+        None => true,
+    };
+    let subnet = matches.iter()
+        .find(|subnet| reachable(subnet))
+        .or_else(|| matches.last())?;
+    let node = owner(subnet);
+
+    Some(Lookup {
+        address: address.clone(),
+        subnet: (*subnet).clone(),
+        owner: subnet.owner.clone(),
+        reachable: reachable(subnet),
+        nexthop: node.and_then(|node| node.nexthop.clone()),
+        via: node.and_then(|node| node.via.clone()),
+        matches: matches.into_iter().cloned().collect(),
+    })
+}
@@ -1,15 +1,30 @@
 pub mod nodes;
 pub mod links;
+pub mod audit;
+pub mod conflicts;
+pub mod connections;
+pub mod info;
+pub mod lookup;
+pub mod options;
+pub mod subnets;
+pub mod trace;
 
-use self::nodes::Node;
+use self::nodes::{Node, Traffic};
 use self::links::Link;
+use self::connections::Connection;
+use self::subnets::Subnet;
 
-use crate::tinc_tcp_stream::{SourceEdge, SourceSubnet, SourceNode};
+use crate::interface::InterfaceStats;
+use crate::tinc_tcp_stream::{DaemonIdentity, SourceConnection, SourceEdge, SourceSubnet, SourceNode, SourceTraffic};
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Data {
-    nodes: Vec<Node>,
-    links: Vec<Link>
+    pub nodes: Vec<Node>,
+    pub links: Vec<Link>,
+    pub subnets: Vec<Subnet>,
+    pub connections: Vec<Connection>,
+    pub daemon: Option<DaemonIdentity>,
+    pub interface: Option<InterfaceStats>,
 }
 impl Data {
     pub fn new (
@@ -17,25 +32,58 @@ impl Data {
         source_subnets:     Vec<SourceSubnet>,
         source_edge:        Vec<SourceEdge>,
     ) -> Self {
-        let mut nodes = Node::load_nodes(source_nodes, source_subnets);
+        let subnets = Subnet::load_subnets(&source_subnets);
+        let mut nodes = Node::load_nodes(source_nodes, &subnets);
         let links = Link::load_links(source_edge, &mut nodes);
         let mut data = Data {
             nodes,
             links,
+            subnets,
+            connections: vec![],
+            daemon: None,
+            interface: None,
         };
         data.frac();
         data
     }
 
+    pub fn set_daemon(&mut self, daemon: DaemonIdentity) {
+        for node in self.nodes.iter_mut() {
+            node.local = node.name == daemon.name;
+            node.classify(Some(&daemon.name));
+        }
+        self.daemon = Some(daemon);
+    }
+
+    pub fn set_connections(&mut self, source_connections: &[SourceConnection]) {
+        self.connections = source_connections.iter().map(Connection::from).collect();
+    }
+
+    pub fn set_traffic(&mut self, source_traffic: &[SourceTraffic]) {
+        for node in self.nodes.iter_mut() {
+            node.traffic = source_traffic.iter()
+                .find(|x| x.node == node.name)
+                .map(Traffic::from);
+        }
+    }
+
     pub fn frac(&mut self) {
         let mut max_werght: u32 = 1;
         for link in &self.links {
             if link.weight > max_werght {
-                max_werght = link.weight.clone();
+                max_werght = link.weight;
             }
         }
         for i in 0..self.links.len() {
-            self.links[i].frac = 1.0 - (((self.links[i].weight.clone() as f64 ) * 100.0) / (max_werght as f64)) / 100.0;
+            self.links[i].frac = 1.0 - (((self.links[i].weight as f64 ) * 100.0) / (max_werght as f64)) / 100.0;
         }
     }
-}
\ No newline at end of file
+}
+
+/// tincd prints `unknown` for addresses it has not learned yet.
+fn known(addr: &str) -> Option<String> {
+    if addr.is_empty() || addr == "unknown" {
+        return None;
+    }
+    Some(addr.to_string())
+}
@@ -1,6 +1,99 @@
-use std::collections::HashMap;
 use std::io::Result;
-use crate::tinc_tcp_stream::{SourceNode, SourceSubnet};
+use crate::domain::known;
+use crate::domain::options::Options;
+use crate::domain::subnets::Subnet;
+use crate::tinc_tcp_stream::{SourceNode, SourceTraffic};
+
+// Bits of node_status_t in tinc's node.h.
+const STATUS_VALIDKEY: u32 = 1 << 1;
+const STATUS_WAITINGFORKEY: u32 = 1 << 2;
+const STATUS_REACHABLE: u32 = 1 << 4;
+const STATUS_INDIRECT: u32 = 1 << 5;
+const STATUS_SPTPS: u32 = 1 << 6;
+const STATUS_UDP_CONFIRMED: u32 = 1 << 7;
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeStatus {
+    pub validkey:       bool,
+    pub waitingforkey:  bool,
+    pub reachable:      bool,
+    pub indirect:       bool,
+    pub sptps:          bool,
+    pub udp_confirmed:  bool,
+}
+impl NodeStatus {
+    pub fn from_bits(status: u32) -> Self {
+        NodeStatus {
+            validkey: status & STATUS_VALIDKEY != 0,
+            waitingforkey: status & STATUS_WAITINGFORKEY != 0,
+            reachable: status & STATUS_REACHABLE != 0,
+            indirect: status & STATUS_INDIRECT != 0,
+            sptps: status & STATUS_SPTPS != 0,
+            udp_confirmed: status & STATUS_UDP_CONFIRMED != 0,
+        }
+    }
+}
+
+/// How packets for a node leave the local daemon.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataPath {
+    Local,
+    Unreachable,
+    /// No session key yet, so nothing can be sent.
+    Pending,
+    /// Straight to the node over UDP that it confirmed working.
+    Udp,
+    /// Straight to the node, but inside the TCP meta connection.
+    Tcp,
+    /// Through the `relay` node, over UDP or meta connections.
+    Relayed,
+}
+
+/// Counters of `dump_traffic`, reset when tincd restarts.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Traffic {
+    pub in_packets:     u64,
+    pub in_bytes:       u64,
+    pub out_packets:    u64,
+    pub out_bytes:      u64,
+}
+impl Traffic {
+    pub fn from(source_traffic: &SourceTraffic) -> Self {
+        Traffic {
+            in_packets: source_traffic.in_packets.parse().unwrap_or(0),
+            in_bytes: source_traffic.in_bytes.parse().unwrap_or(0),
+            out_packets: source_traffic.out_packets.parse().unwrap_or(0),
+            out_bytes: source_traffic.out_bytes.parse().unwrap_or(0),
+        }
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.in_bytes + self.out_bytes
+    }
+
+    pub fn add(&mut self, other: &Traffic) {
+        self.in_packets += other.in_packets;
+        self.in_bytes += other.in_bytes;
+        self.out_packets += other.out_packets;
+        self.out_bytes += other.out_bytes;
+    }
+
+    /// What was counted since `last`, or nothing if a counter went
+    /// backwards because tincd restarted.
+    pub fn since(&self, last: &Traffic) -> Option<Traffic> {
+        if self.in_packets < last.in_packets || self.in_bytes < last.in_bytes
+            || self.out_packets < last.out_packets || self.out_bytes < last.out_bytes {
+            return None;
+        }
+        Some(Traffic {
+            in_packets: self.in_packets - last.in_packets,
+            in_bytes: self.in_bytes - last.in_bytes,
+            out_packets: self.out_packets - last.out_packets,
+            out_bytes: self.out_bytes - last.out_bytes,
+        })
+    }
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Node {
@@ -10,8 +103,27 @@ pub struct Node {
     pub name:       String,
     pub version:    u8,
     pub group:      u8,
-    pub nets:       Vec<String>,
+    pub nets:       Vec<Subnet>,
     pub id:         u32,
+    pub local:      bool,
+    pub nexthop:    Option<String>,
+    pub via:        Option<String>,
+    pub address:    Option<String>,
+    pub port:       Option<u16>,
+    pub cipher:     i32,
+    pub digest:     i32,
+    pub maclength:  i32,
+    pub compression: i32,
+    pub options:    Options,
+    pub status:     NodeStatus,
+    pub distance:   i32,
+    pub pmtu:       u16,
+    pub minmtu:     u16,
+    pub maxmtu:     u16,
+    pub last_state_change: i64,
+    pub traffic:    Option<Traffic>,
+    pub data_path:  DataPath,
+    pub relay:      Option<String>,
 }
 impl Node {
     fn from(source_node: &SourceNode) -> Result<Self> {
@@ -26,41 +138,85 @@ impl Node {
             group: 0,
             nets: vec![],
             id: 0,
+            local: false,
+            nexthop: node_name(&source_node.nexthop),
+            via: node_name(&source_node.via),
+            address: known(&source_node.host),
+            port: source_node.port.parse().ok(),
+            cipher: source_node.cipher.parse().unwrap_or(0),
+            digest: source_node.digest.parse().unwrap_or(0),
+            maclength: source_node.maclength.parse().unwrap_or(0),
+            compression: source_node.compression.parse().unwrap_or(0),
+            options: Options::from_bits(u32::from_str_radix(&source_node.options, 16).unwrap_or(0)),
+            status: NodeStatus::from_bits(status_int as u32),
+            distance: source_node.distance.parse().unwrap_or(0),
+            pmtu: source_node.pmtu.parse().unwrap_or(0),
+            minmtu: source_node.minmtu.parse().unwrap_or(0),
+            maxmtu: source_node.maxmtu.parse().unwrap_or(0),
+            last_state_change: source_node.last_state_change.parse().unwrap_or(0),
+            traffic: None,
+            data_path: DataPath::Unreachable,
+            relay: None,
         })
     }
 
+    /// Classify the data path from the status, options, `via` and `nexthop`.
+    /// UDP goes through `via`, TCP follows the meta connections to `nexthop`.
+    /// tincd sets `via` to itself for neighbours it cannot send UDP to
+    /// directly, so the `local` node is no relay.
+    pub fn classify(&mut self, local: Option<&str>) {
+        let other = |name: &Option<String>| name.as_ref()
+            .filter(|x| **x != self.name && Some(x.as_str()) != local)
+            .cloned();
+        let tcp = self.options.tcponly || !self.status.udp_confirmed;
+        let (data_path, relay) = if self.local {
+            (DataPath::Local, None)
+        } else if self.reachable == 0 {
+            (DataPath::Unreachable, None)
+        } else if let Some(via) = other(&self.via) {
+            (DataPath::Relayed, Some(via))
+        } else if !self.status.validkey {
+            (DataPath::Pending, None)
+        } else if tcp && other(&self.nexthop).is_some() {
+            (DataPath::Relayed, other(&self.nexthop))
+        } else if tcp {
+            (DataPath::Tcp, None)
+        } else {
+            (DataPath::Udp, None)
+        };
+        self.data_path = data_path;
+        self.relay = relay;
+    }
+
     pub fn load_nodes(
         source_nodes: Vec<SourceNode>,
-        source_subnets: Vec<SourceSubnet>
+        subnets: &[Subnet]
     ) -> Vec<Node> {
-        let mut subnets = HashMap::new();
-        for subnet in source_subnets {
-            if subnets.contains_key(&subnet.name) {
-                let addrs: &Vec<String> = &subnets[&subnet.name];
-                let mut addrs = addrs.clone();
-                addrs.push(subnet.addr);
-                subnets.insert(subnet.name, addrs);
-            }
-            else {
-                subnets.insert(subnet.name, vec![subnet.addr]);
-            }
-        }
-
         let mut nodes: Vec<Self> = vec![];
 
         let mut index = 0;
         for source_node in source_nodes {
             if let Ok(mut node) = Self::from(&source_node) {
+                node.classify(None);
                 index += 1;
                 node.id = index;
                 node.index = index;
-                if subnets.contains_key(&node.name) {
-                    node.nets = subnets[&node.name].clone();
-                }
+                node.nets = subnets.iter()
+                    .filter(|subnet| subnet.owner.as_ref() == Some(&node.name))
+                    .cloned()
+                    .collect();
                 nodes.push(node);
             }
         }
-        return nodes;
+        nodes
+    }
+}
+
+// Unreachable nodes have "-" as nexthop and via.
+fn node_name(name: &str) -> Option<String> {
+    match name {
+        "-" => None,
+        _ => Some(name.to_string()),
     }
 }
 
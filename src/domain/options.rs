@@ -0,0 +1,27 @@
+// OPTION_* from tinc's connection.h.
+const OPTION_INDIRECT: u32 = 0x0001;
+const OPTION_TCPONLY: u32 = 0x0002;
+const OPTION_PMTU_DISCOVERY: u32 = 0x0004;
+const OPTION_CLAMP_MSS: u32 = 0x0008;
+
+/// The option bits nodes, edges and connections are announced with. The top
+/// byte carries the protocol minor version.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Options {
+    pub indirect:           bool,
+    pub tcponly:            bool,
+    pub pmtu_discovery:     bool,
+    pub clamp_mss:          bool,
+    pub protocol_minor:     u8,
+}
+impl Options {
+    pub fn from_bits(options: u32) -> Self {
+        Options {
+            indirect: options & OPTION_INDIRECT != 0,
+            tcponly: options & OPTION_TCPONLY != 0,
+            pmtu_discovery: options & OPTION_PMTU_DISCOVERY != 0,
+            clamp_mss: options & OPTION_CLAMP_MSS != 0,
+            protocol_minor: (options >> 24) as u8,
+        }
+    }
+}
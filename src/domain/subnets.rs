@@ -0,0 +1,156 @@
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::tinc_tcp_stream::SourceSubnet;
+
+pub const DEFAULT_WEIGHT: i32 = 10;
+pub const BROADCAST_OWNER: &str = "(broadcast)";
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Net {
+    Ipv4 { address: Ipv4Addr, prefix: u8 },
+    Ipv6 { address: Ipv6Addr, prefix: u8 },
+    Mac { address: String },
+}
+impl Net {
+    pub fn prefix(&self) -> u8 {
+        match self {
+            Net::Ipv4 { prefix, .. } | Net::Ipv6 { prefix, .. } => *prefix,
+            Net::Mac { .. } => 48,
+        }
+    }
+
+    pub fn ip(&self) -> Option<IpAddr> {
+        match self {
+            Net::Ipv4 { address, .. } => Some(IpAddr::V4(*address)),
+            Net::Ipv6 { address, .. } => Some(IpAddr::V6(*address)),
+            Net::Mac { .. } => None,
+        }
+    }
+
+    /// Whether `addr` falls inside this net. MAC nets only match themselves.
+    pub fn contains(&self, addr: &Net) -> bool {
+        match (self, addr) {
+            (Net::Ipv4 { address: net, prefix }, Net::Ipv4 { address, prefix: len }) => {
+                let mask = u32::MAX.checked_shl(32 - *prefix as u32).unwrap_or(0);
+                prefix <= len && u32::from(*net) & mask == u32::from(*address) & mask
+            }
+            (Net::Ipv6 { address: net, prefix }, Net::Ipv6 { address, prefix: len }) => {
+                let mask = u128::MAX.checked_shl(128 - *prefix as u32).unwrap_or(0);
+                prefix <= len && u128::from(*net) & mask == u128::from(*address) & mask
+            }
+            (Net::Mac { address: net }, Net::Mac { address }) => net == address,
+            _ => false,
+        }
+    }
+
+    /// A bare IPv4, IPv6 or MAC address, as given to `tinc info`.
+    pub fn parse_address(addr: &str) -> Option<Self> {
+        if addr.contains('/') {
+            return None;
+        }
+        Self::parse(addr)
+    }
+
+    fn parse(net: &str) -> Option<Self> {
+        let mut iter = net.splitn(2, '/');
+        let address = iter.next()?;
+        let prefix = iter.next();
+        if let Some(mac) = parse_mac(address) {
+            return match prefix {
+                None => Some(Net::Mac { address: mac }),
+                Some(_) => None,
+            };
+        }
+        let prefix = |max: u8| match prefix {
+            Some(prefix) => prefix.parse().ok().filter(|x| *x <= max),
+            None => Some(max),
+        };
+        match address.parse().ok()? {
+            IpAddr::V4(address) => Some(Net::Ipv4 { address, prefix: prefix(32)? }),
+            IpAddr::V6(address) => Some(Net::Ipv6 { address, prefix: prefix(128)? }),
+        }
+    }
+}
+impl fmt::Display for Net {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Net::Ipv4 { address, prefix: 32 } => write!(f, "{}", address),
+            Net::Ipv4 { address, prefix } => write!(f, "{}/{}", address, prefix),
+            Net::Ipv6 { address, prefix: 128 } => write!(f, "{}", address),
+            Net::Ipv6 { address, prefix } => write!(f, "{}/{}", address, prefix),
+            Net::Mac { address } => write!(f, "{}", address),
+        }
+    }
+}
+
+fn parse_mac(addr: &str) -> Option<String> {
+    let octets: Vec<&str> = addr.split(':').collect();
+    if octets.len() != 6 || addr.contains("::") {
+        return None;
+    }
+    let mut mac = vec![];
+    for octet in octets {
+        if octet.is_empty() || octet.len() > 2 {
+            return None;
+        }
+        mac.push(format!("{:02x}", u8::from_str_radix(octet, 16).ok()?));
+    }
+    Some(mac.join(":"))
+}
+
+/// A subnet as dumped by tincd: `<net>[#weight]`, owned by a node or by
+/// nobody for the `(broadcast)` subnets.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Subnet {
+    #[serde(flatten)]
+    pub net:        Net,
+    pub weight:     i32,
+    pub owner:      Option<String>,
+}
+impl Subnet {
+    pub fn parse(subnet: &str, owner: &str) -> Option<Self> {
+        let mut iter = subnet.splitn(2, '#');
+        let net = Net::parse(iter.next()?)?;
+        let weight = match iter.next() {
+            Some(weight) => weight.parse().ok()?,
+            None => DEFAULT_WEIGHT,
+        };
+        let owner = match owner {
+            BROADCAST_OWNER => None,
+            _ => Some(owner.to_string()),
+        };
+        Some(Subnet { net, weight, owner })
+    }
+
+    pub fn from(source_subnet: &SourceSubnet) -> Option<Self> {
+        Self::parse(&source_subnet.addr, &source_subnet.name)
+    }
+
+    pub fn is_broadcast(&self) -> bool {
+        self.owner.is_none()
+    }
+
+    /// Every parseable subnet, sorted. Unparseable ones are logged and dropped.
+    pub fn load_subnets(source_subnets: &[SourceSubnet]) -> Vec<Subnet> {
+        let mut subnets = vec![];
+        for source_subnet in source_subnets {
+            match Self::from(source_subnet) {
+                Some(subnet) => subnets.push(subnet),
+                None => warn!("Invalid subnet {} of {}", source_subnet.addr, source_subnet.name),
+            }
+        }
+        subnets.sort();
+        subnets
+    }
+}
+impl fmt::Display for Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.net)?;
+        if self.weight != DEFAULT_WEIGHT {
+            write!(f, "#{}", self.weight)?;
+        }
+        Ok(())
+    }
+}
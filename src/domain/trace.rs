@@ -0,0 +1,139 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::domain::Data;
+use crate::domain::lookup::lookup;
+use crate::domain::nodes::Node;
+use crate::domain::subnets::Net;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Path {
+    pub hops:       Vec<String>,
+    pub weight:     u32,
+}
+
+/// The route from the local node to a destination node.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Trace {
+    pub source:         String,
+    pub destination:    String,
+    pub reachable:      bool,
+    pub nexthop:        Option<String>,
+    pub via:            Option<String>,
+    /// The route tincd uses, rebuilt hop by hop from the distance and
+    /// nexthop of every node.
+    pub route:          Option<Path>,
+    /// The path with the lowest total edge weight.
+    pub shortest:       Option<Path>,
+}
+
+/// Trace the route to `target`, a node name or an address owned by a node.
+pub fn trace(data: &Data, target: &str) -> Option<Trace> {
+    let source = data.nodes.iter().find(|node| node.local)?;
+    let destination = match data.nodes.iter().find(|node| node.name == target) {
+        Some(node) => node,
+        None => {
+            let owner = Net::parse_address(target)
+                .and_then(|address| lookup(data, &address))
+                .and_then(|found| found.owner)?;
+            data.nodes.iter().find(|node| node.name == owner)?
+        }
+    };
+    let graph = Graph::new(data);
+    Some(Trace {
+        source: source.name.clone(),
+        destination: destination.name.clone(),
+        reachable: destination.reachable != 0,
+        nexthop: destination.nexthop.clone(),
+        via: destination.via.clone(),
+        route: graph.route(data, destination),
+        shortest: graph.shortest(&source.name, &destination.name),
+    })
+}
+
+/// Directed edge weights. Like tincd, an edge is only usable when the
+/// other side announces the reverse edge too.
+struct Graph<'a> {
+    edges: HashMap<&'a str, Vec<(&'a str, u32)>>,
+}
+impl<'a> Graph<'a> {
+    fn new(data: &'a Data) -> Self {
+        let mut edges: HashMap<&str, Vec<(&str, u32)>> = HashMap::new();
+        for link in data.links.iter() {
+            if let (Some(forward), Some(reverse)) = (&link.forward, &link.reverse) {
+                edges.entry(&link.sname).or_default().push((&link.tname, forward.weight));
+                edges.entry(&link.tname).or_default().push((&link.sname, reverse.weight));
+            }
+        }
+        Graph { edges }
+    }
+
+    fn weight(&self, from: &str, to: &str) -> Option<u32> {
+        self.edges.get(from)?.iter().find(|(x, _)| *x == to).map(|(_, weight)| *weight)
+    }
+
+    fn path(&self, hops: Vec<String>) -> Option<Path> {
+        let mut weight = 0;
+        for pair in hops.windows(2) {
+            weight += self.weight(&pair[0], &pair[1])?;
+        }
+        Some(Path { hops, weight })
+    }
+
+    /// Walk back from the destination through neighbours one hop closer
+    /// that share its nexthop, until the local node is reached.
+    fn route(&self, data: &Data, destination: &Node) -> Option<Path> {
+        if destination.reachable == 0 {
+            return None;
+        }
+        let nodes: HashMap<&str, &Node> = data.nodes.iter().map(|node| (node.name.as_str(), node)).collect();
+        let distance = |node: &Node| if node.local { 0 } else { node.distance };
+        let mut hops = vec![destination.name.clone()];
+        let mut current = destination;
+        while !current.local {
+            let previous = self.edges.get(current.name.as_str())?.iter()
+                .filter_map(|(name, _)| nodes.get(name))
+                .filter(|node| node.reachable != 0 && distance(node) == distance(current) - 1)
+                .filter(|node| node.local || node.nexthop == destination.nexthop)
+                .min_by_key(|node| (self.weight(&node.name, &current.name), &node.name))?;
+            hops.push(previous.name.clone());
+            current = previous;
+        }
+        hops.reverse();
+        self.path(hops)
+    }
+
+    fn shortest(&self, source: &str, destination: &str) -> Option<Path> {
+        let mut dist: HashMap<&str, u32> = HashMap::new();
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = BinaryHeap::new();
+        dist.insert(source, 0);
+        queue.push(Reverse((0, source)));
+        while let Some(Reverse((weight, name))) = queue.pop() {
+            if name == destination {
+                break;
+            }
+            if dist.get(name).map(|x| weight > *x).unwrap_or(false) {
+                continue;
+            }
+            for (next, edge_weight) in self.edges.get(name).into_iter().flatten() {
+                let next_weight = weight + edge_weight;
+                if dist.get(next).map(|x| next_weight < *x).unwrap_or(true) {
+                    dist.insert(next, next_weight);
+                    previous.insert(next, name);
+                    queue.push(Reverse((next_weight, next)));
+                }
+            }
+        }
+
+        dist.get(destination)?;
+        let mut hops = vec![destination.to_string()];
+        let mut current = destination;
+        while current != source {
+            current = previous.get(current)?;
+            hops.push(current.to_string());
+        }
+        hops.reverse();
+        self.path(hops)
+    }
+}
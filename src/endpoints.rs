@@ -0,0 +1,116 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::domain::Data;
+use crate::events::{Event, EventKind};
+
+const ENDPOINT_HISTORY_LEN: usize = 20;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EndpointRecord {
+    pub address:    String,
+    pub first_seen: i64,
+    pub last_seen:  i64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeEndpoint {
+    pub name:               String,
+    /// `address port N` tincd sends UDP packets to.
+    pub endpoint:           Option<String>,
+    /// Addresses the node announces for itself on its edges.
+    pub local_addresses:    Vec<String>,
+    /// Addresses its peers see it connecting from.
+    pub seen_as:            Vec<String>,
+    pub behind_nat:         bool,
+    pub unknown:            bool,
+    pub history:            Vec<EndpointRecord>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EndpointReport {
+    pub nodes:      Vec<NodeEndpoint>,
+}
+
+/// Remembers the endpoints every node was seen at.
+#[derive(Default)]
+pub struct EndpointTracker {
+    history:        HashMap<String, Vec<EndpointRecord>>,
+}
+impl EndpointTracker {
+    pub fn new() -> Self {
+        EndpointTracker::default()
+    }
+
+    /// Record the current endpoints. The events describe nodes whose
+    /// endpoint changed since the previous update.
+    pub fn update(&mut self, data: &Data, time: i64) -> (EndpointReport, Vec<Event>) {
+        let mut local_addresses: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        let mut seen_as: HashMap<&str, BTreeSet<String>> = HashMap::new();
+        for link in data.links.iter() {
+            let directions = [(&link.sname, &link.tname, &link.forward), (&link.tname, &link.sname, &link.reverse)];
+            for (from, to, direction) in directions.iter() {
+                if let Some(direction) = direction {
+                    if let Some(ref address) = direction.local_address {
+                        local_addresses.entry(from.as_str()).or_default().insert(address.clone());
+                    }
+                    if let Some(ref address) = direction.address {
+                        seen_as.entry(to.as_str()).or_default().insert(address.clone());
+                    }
+                }
+            }
+        }
+
+        let mut report = EndpointReport::default();
+        let mut events = vec![];
+        for node in data.nodes.iter() {
+            let endpoint = match (&node.address, node.port) {
+                (Some(address), Some(port)) if !node.local => Some(format!("{} port {}", address, port)),
+                _ => None,
+            };
+            let history = self.history.entry(node.name.clone()).or_default();
+            if let Some(ref endpoint) = endpoint {
+                match history.last_mut() {
+                    Some(last) if &last.address == endpoint => last.last_seen = time,
+                    last => {
+                        if let Some(last) = last {
+                            events.push(Event {
+                                time,
+                                node: node.name.clone(),
+                                kind: EventKind::EndpointChanged {
+                                    from: last.address.clone(),
+                                    to: endpoint.clone(),
+                                },
+                            });
+                        }
+                        if history.len() >= ENDPOINT_HISTORY_LEN {
+                            history.remove(0);
+                        }
+                        history.push(EndpointRecord {
+                            address: endpoint.clone(),
+                            first_seen: time,
+                            last_seen: time,
+                        });
+                    }
+                }
+            }
+
+            let local: Vec<String> = local_addresses.remove(node.name.as_str())
+                .unwrap_or_default().into_iter().collect();
+            let seen: Vec<String> = seen_as.remove(node.name.as_str())
+                .unwrap_or_default().into_iter().collect();
+            // Peers seeing an address the node does not know it has.
+            let behind_nat = !local.is_empty() && seen.iter().any(|x| !local.contains(x));
+            report.nodes.push(NodeEndpoint {
+                name: node.name.clone(),
+                unknown: !node.local && endpoint.is_none() && seen.is_empty(),
+                endpoint,
+                local_addresses: local,
+                seen_as: seen,
+                behind_nat,
+                history: history.clone(),
+            });
+        }
+        (report, events)
+    }
+}
+
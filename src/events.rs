@@ -0,0 +1,51 @@
+use std::collections::VecDeque;
+
+use crate::accounting::Period;
+
+const EVENT_LOG_LEN: usize = 500;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventKind {
+    /// The address tincd sends UDP packets for the node to changed.
+    EndpointChanged { from: String, to: String },
+    Reachable,
+    Unreachable,
+    /// A link between the node and `peer` appeared in the edge dump.
+    LinkAdded { peer: String },
+    LinkRemoved { peer: String },
+    /// The node, or its link to `peer`, changed `score` times within the
+    /// flap window. Its changes are not reported until it settles.
+    FlapStarted { peer: Option<String>, score: usize },
+    /// It stayed `up`, reachable or present, for a whole window.
+    FlapStopped { peer: Option<String>, up: bool, suppressed: usize },
+    /// Traffic of the day or month passed `level` percent of the quota.
+    QuotaWarning { period: Period, level: u8, used: u64, limit: u64 },
+    /// Bytes per second went over the configured maximum.
+    RateExceeded { rate: u64, limit: u64 },
+    /// A node missing from the allowlist showed up in the node dump.
+    UnknownNode { reachable: bool },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub time:       i64,
+    pub node:       String,
+    #[serde(flatten)]
+    pub kind:       EventKind,
+}
+
+/// The most recent events, oldest first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct EventLog {
+    pub events:     VecDeque<Event>,
+}
+impl EventLog {
+    pub fn push(&mut self, event: Event) {
+        info!("Event: {:?}", event);
+        if self.events.len() >= EVENT_LOG_LEN {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+}
@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::domain::Data;
+use crate::events::{Event, EventKind};
+
+pub const DEFAULT_FLAP_WINDOW: &str = "300";
+pub const DEFAULT_FLAP_THRESHOLD: &str = "4";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlapSubject {
+    Node,
+    Link,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FlapState {
+    pub subject:        FlapSubject,
+    /// Node name, or `sname-tname` of a link.
+    pub name:           String,
+    /// Transitions within the window.
+    pub score:          usize,
+    pub flapping:       bool,
+    /// Change events held back since the subject started flapping.
+    pub suppressed:     usize,
+    pub last_change:    i64,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct FlapReport {
+    pub window:     i64,
+    pub threshold:  usize,
+    /// Subjects that changed within the window or are still flapping.
+    pub states:     Vec<FlapState>,
+}
+impl FlapReport {
+    pub fn flapping(&self) -> impl Iterator<Item = &FlapState> {
+        self.states.iter().filter(|x| x.flapping)
+    }
+}
+
+struct History {
+    up:             bool,
+    transitions:    VecDeque<i64>,
+    flapping:       bool,
+    suppressed:     usize,
+    last_change:    i64,
+}
+
+/// Counts node reachability changes and links appearing or disappearing
+/// over successive snapshots. A subject with `threshold` transitions
+/// within `window` seconds is flapping, and its change events are
+/// suppressed until it stays put for a whole window.
+pub struct FlapDetector {
+    window:         i64,
+    threshold:      usize,
+    history:        BTreeMap<(FlapSubject, String), History>,
+    seeded:         bool,
+}
+impl FlapDetector {
+    pub fn new(window: i64, threshold: usize) -> Self {
+        FlapDetector {
+            window,
+            threshold,
+            history: BTreeMap::new(),
+            seeded: false,
+        }
+    }
+
+    pub fn update(&mut self, data: &Data, time: i64) -> (FlapReport, Vec<Event>) {
+        let mut current: BTreeMap<(FlapSubject, String), bool> = BTreeMap::new();
+        for node in data.nodes.iter().filter(|x| !x.local) {
+            current.insert((FlapSubject::Node, node.name.clone()), node.reachable == 1);
+        }
+        for link in data.links.iter() {
+            current.insert((FlapSubject::Link, link._hash.clone()), true);
+        }
+        // Links missing from this dump are down, nodes keep their last state.
+        for (key, history) in self.history.iter() {
+            if key.0 == FlapSubject::Link && !current.contains_key(key) {
+                current.insert(key.clone(), false);
+            }
+            if key.0 == FlapSubject::Node && !current.contains_key(key) {
+                current.insert(key.clone(), history.up);
+            }
+        }
+
+        let oldest = time - self.window;
+        let seeded = self.seeded;
+        self.seeded = true;
+        let mut events = vec![];
+        for ((subject, name), up) in current {
+            // The first update only learns the state. Anything new after it
+            // was absent, that is down, before.
+            let history = self.history.entry((subject, name.clone())).or_insert_with(|| History {
+                up: up && !seeded,
+                transitions: VecDeque::new(),
+                flapping: false,
+                suppressed: 0,
+                last_change: time,
+            });
+            while history.transitions.front().is_some_and(|x| *x <= oldest) {
+                history.transitions.pop_front();
+            }
+            let (node, peer) = split(subject, &name);
+            let kind = if history.up != up {
+                history.up = up;
+                history.last_change = time;
+                history.transitions.push_back(time);
+                if history.flapping {
+                    history.suppressed += 1;
+                    None
+                } else if history.transitions.len() >= self.threshold {
+                    history.flapping = true;
+                    Some(EventKind::FlapStarted { peer, score: history.transitions.len() })
+                } else {
+                    Some(change(subject, up, peer))
+                }
+            } else if history.flapping && history.transitions.is_empty() {
+                history.flapping = false;
+                let suppressed = history.suppressed;
+                history.suppressed = 0;
+                Some(EventKind::FlapStopped { peer, up, suppressed })
+            } else {
+                None
+            };
+            if let Some(kind) = kind {
+                events.push(Event { time, node, kind });
+            }
+        }
+
+        let mut states = vec![];
+        for ((subject, name), history) in self.history.iter() {
+            if history.transitions.is_empty() && !history.flapping {
+                continue;
+            }
+            states.push(FlapState {
+                subject: *subject,
+                name: name.clone(),
+                score: history.transitions.len(),
+                flapping: history.flapping,
+                suppressed: history.suppressed,
+                last_change: history.last_change,
+            });
+        }
+        // Forget links that are gone for good.
+        self.history.retain(|key, history| key.0 == FlapSubject::Node || history.up || history.flapping
+            || !history.transitions.is_empty());
+        let report = FlapReport {
+            window: self.window,
+            threshold: self.threshold,
+            states,
+        };
+        (report, events)
+    }
+}
+
+fn change(subject: FlapSubject, up: bool, peer: Option<String>) -> EventKind {
+    match (subject, up) {
+        (FlapSubject::Node, true) => EventKind::Reachable,
+        (FlapSubject::Node, false) => EventKind::Unreachable,
+        (FlapSubject::Link, true) => EventKind::LinkAdded { peer: peer.unwrap_or_default() },
+        (FlapSubject::Link, false) => EventKind::LinkRemoved { peer: peer.unwrap_or_default() },
+    }
+}
+
+/// The node an event is about, and the other end for links. Node names
+/// can not contain `-`, so the link name splits back unambiguously.
+fn split(subject: FlapSubject, name: &str) -> (String, Option<String>) {
+    match subject {
+        FlapSubject::Node => (name.to_string(), None),
+        FlapSubject::Link => {
+            let mut iter = name.splitn(2, '-');
+            let sname = iter.next().unwrap_or_default().to_string();
+            (sname, iter.next().map(str::to_string))
+        }
+    }
+}
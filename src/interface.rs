@@ -0,0 +1,93 @@
+use std::collections::VecDeque;
+use std::fs;
+use std::io::Result;
+use std::path::Path;
+
+use crate::tinc_conf;
+
+pub const SYSFS_NET: &str = "/sys/class/net";
+const INTERFACE_HISTORY_LEN: usize = 180;
+
+/// Find the tun/tap interface tincd uses: the `Interface` variable of
+/// `tinc.conf`, else the netname like tincd does on Linux. Without either
+/// the kernel picked the name, and there is nothing to go by.
+pub fn interface_name(confdir: &Path, netname: Option<&str>) -> Option<String> {
+    if let Ok(Some(interface)) = tinc_conf::read_variable(&confdir.join("tinc.conf"), "Interface") {
+        return Some(interface);
+    }
+    netname.map(str::to_string)
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InterfaceStats {
+    pub name:           String,
+    pub time:           i64,
+    pub mtu:            u32,
+    pub rx_bytes:       u64,
+    pub tx_bytes:       u64,
+    pub rx_packets:     u64,
+    pub tx_packets:     u64,
+    pub rx_errors:      u64,
+    pub tx_errors:      u64,
+    pub rx_dropped:     u64,
+    pub tx_dropped:     u64,
+    pub rx_rate:        Option<f64>,
+    pub tx_rate:        Option<f64>,
+}
+impl InterfaceStats {
+    /// Read `/sys/class/net/<name>/`, or the same layout under `sysfs`.
+    pub fn read(sysfs: &Path, name: &str, time: i64) -> Result<Self> {
+        let dir = sysfs.join(name);
+        let mtu = read_number(&dir.join("mtu"))? as u32;
+        let statistics = dir.join("statistics");
+        let counter = |x: &str| read_number(&statistics.join(x));
+        Ok(InterfaceStats {
+            name: name.to_string(),
+            time,
+            mtu,
+            rx_bytes: counter("rx_bytes")?,
+            tx_bytes: counter("tx_bytes")?,
+            rx_packets: counter("rx_packets")?,
+            tx_packets: counter("tx_packets")?,
+            rx_errors: counter("rx_errors")?,
+            tx_errors: counter("tx_errors")?,
+            rx_dropped: counter("rx_dropped")?,
+            tx_dropped: counter("tx_dropped")?,
+            rx_rate: None,
+            tx_rate: None,
+        })
+    }
+}
+
+fn read_number(path: &Path) -> Result<u64> {
+    let value = fs::read_to_string(path)?;
+    Ok(value.trim().parse().unwrap_or(0))
+}
+
+/// The last samples of one interface, oldest first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InterfaceSeries {
+    pub samples:    VecDeque<InterfaceStats>,
+}
+impl InterfaceSeries {
+    /// Append a sample, filling in its byte rates from the previous one.
+    /// Counters that went backwards (interface recreated) give no rate.
+    pub fn push(&mut self, mut sample: InterfaceStats) -> &InterfaceStats {
+        if let Some(last) = self.samples.back().filter(|x| x.name == sample.name) {
+            let secs = (sample.time - last.time) as f64;
+            if secs > 0.0 && sample.rx_bytes >= last.rx_bytes && sample.tx_bytes >= last.tx_bytes {
+                sample.rx_rate = Some((sample.rx_bytes - last.rx_bytes) as f64 / secs);
+                sample.tx_rate = Some((sample.tx_bytes - last.tx_bytes) as f64 / secs);
+            }
+        }
+        if self.samples.len() >= INTERFACE_HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.samples.back().unwrap()
+    }
+
+    pub fn latest(&self) -> Option<&InterfaceStats> {
+        self.samples.back()
+    }
+}
@@ -0,0 +1,143 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::domain::Data;
+
+pub const DEFAULT_WINDOWS: &str = "5m,1h,1d";
+
+/// Where a latency sample comes from. Older tincd only dumps the edge
+/// weight, which it derives from the round trip time at connect.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LatencySource {
+    AvgRtt,
+    Weight,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LatencySample {
+    pub time:       i64,
+    pub rtt:        f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LatencyStats {
+    /// Window length in seconds.
+    pub window:     i64,
+    pub samples:    usize,
+    pub min:        f64,
+    pub avg:        f64,
+    pub max:        f64,
+    pub p95:        f64,
+}
+impl LatencyStats {
+    fn from(window: i64, samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        // Nearest rank.
+        let rank = (sorted.len() as f64 * 0.95).ceil() as usize;
+        Some(LatencyStats {
+            window,
+            samples: sorted.len(),
+            min: sorted[0],
+            avg: sorted.iter().sum::<f64>() / sorted.len() as f64,
+            max: sorted[sorted.len() - 1],
+            p95: sorted[rank.max(1) - 1],
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LinkLatency {
+    pub sname:      String,
+    pub tname:      String,
+    pub _hash:      String,
+    pub source:     LatencySource,
+    /// Latest round trip time in milliseconds.
+    pub rtt:        f64,
+    pub stats:      Vec<LatencyStats>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct LatencyReport {
+    pub windows:    Vec<i64>,
+    pub links:      Vec<LinkLatency>,
+}
+
+/// Parse a comma separated list of windows like `30s,5m,1h,1d`. A bare
+/// number is taken as seconds.
+pub fn parse_windows(windows: &str) -> Option<Vec<i64>> {
+    let mut parsed = vec![];
+    for window in windows.split(',').map(str::trim) {
+        let (number, unit) = match window.char_indices().last()? {
+            (i, 's') => (&window[..i], 1),
+            (i, 'm') => (&window[..i], 60),
+            (i, 'h') => (&window[..i], 3600),
+            (i, 'd') => (&window[..i], 86400),
+            _ => (window, 1),
+        };
+        let seconds = number.parse::<i64>().ok().filter(|x| *x > 0)? * unit;
+        parsed.push(seconds);
+    }
+    parsed.sort();
+    parsed.dedup();
+    Some(parsed)
+}
+
+/// Keeps the round trip time of every reachable link for as long as the
+/// largest window.
+pub struct LatencyMonitor {
+    windows:        Vec<i64>,
+    history:        HashMap<String, VecDeque<LatencySample>>,
+}
+impl LatencyMonitor {
+    pub fn new(windows: Vec<i64>) -> Self {
+        LatencyMonitor {
+            windows,
+            history: HashMap::new(),
+        }
+    }
+
+    pub fn update(&mut self, data: &Data, time: i64) -> LatencyReport {
+        let retention = self.windows.iter().max().cloned().unwrap_or(0);
+        for samples in self.history.values_mut() {
+            while samples.front().is_some_and(|x| x.time <= time - retention) {
+                samples.pop_front();
+            }
+        }
+        self.history.retain(|_, samples| !samples.is_empty());
+
+        let mut links = vec![];
+        for link in data.links.iter().filter(|x| x.reachable == 1 && x.weight > 0) {
+            let (source, rtt) = match link.avg_rtt {
+                Some(avg_rtt) => (LatencySource::AvgRtt, avg_rtt as f64),
+                None => (LatencySource::Weight, link.weight as f64 / 10.0),
+            };
+            let samples = self.history.entry(link._hash.clone()).or_default();
+            samples.push_back(LatencySample { time, rtt });
+            let stats = self.windows.iter()
+                .filter_map(|window| {
+                    let window_samples: Vec<f64> = samples.iter()
+                        .filter(|x| x.time > time - window)
+                        .map(|x| x.rtt)
+                        .collect();
+                    LatencyStats::from(*window, &window_samples)
+                })
+                .collect();
+            links.push(LinkLatency {
+                sname: link.sname.clone(),
+                tname: link.tname.clone(),
+                _hash: link._hash.clone(),
+                source,
+                rtt,
+                stats,
+            });
+        }
+        LatencyReport {
+            windows: self.windows.clone(),
+            links,
+        }
+    }
+}
@@ -4,14 +4,37 @@ extern crate error_chain;
 extern crate fern;
 #[macro_use]
 extern crate serde;
+#[macro_use]
+extern crate serde_json;
 
 #[macro_use]
 extern crate log;
 
 extern crate derive_try_from_primitive;
+extern crate inotify;
 
 pub mod tinc_tcp_stream;
 pub mod control;
 pub mod logging;
 pub mod domain;
-pub mod web_server;
\ No newline at end of file
+pub mod web_server;
+pub mod purge;
+pub mod state;
+#[cfg(feature = "mock-tincd")]
+pub mod mock_tincd;
+pub mod pidfile;
+pub mod process;
+pub mod metrics;
+pub mod tinc_conf;
+pub mod interface;
+pub mod routes;
+pub mod pmtu;
+pub mod events;
+pub mod endpoints;
+pub mod latency;
+pub mod tsdb;
+pub mod availability;
+pub mod flaps;
+pub mod accounting;
+pub mod quotas;
+pub mod allowlist;
\ No newline at end of file
@@ -59,11 +59,11 @@ impl Formatter {
         if self.output_timestamp {
             DATE_TIME_FORMAT_STR
         } else {
-            &""
+            ""
         }
     }
 
-    fn get_record_level(&self, level: log::Level) -> Box<fmt::Display> {
+    fn get_record_level(&self, level: log::Level) -> Box<dyn fmt::Display> {
         if self.output_color && cfg!(not(windows)) {
             Box::new(COLORS.color(level))
         } else {
@@ -178,7 +178,7 @@ fn check_log_file_exit(
         if let Some(log_file) = log_file {
 
             if !Path::new(&_log_dir).is_dir() {
-                let _ = fs::create_dir_all(&_log_dir);
+                let _ = fs::create_dir_all(_log_dir);
             }
 
             let log_file = _log_dir.join(log_file);
@@ -0,0 +1,223 @@
+use std::fmt::Write;
+
+use crate::domain::audit::Severity;
+use crate::domain::conflicts::ConflictKind;
+use crate::domain::nodes::DataPath;
+use crate::flaps::FlapSubject;
+use crate::routes::RouteProblemKind;
+use crate::state::State;
+
+/// Prometheus text exposition of the current state.
+pub fn render(state: &State) -> String {
+    let mut metrics = Metrics::default();
+
+    metrics.family("tincmgr_attached", "gauge", "Whether the last poll of tincd succeeded.");
+    metrics.sample("tincmgr_attached", &[], state.attach_error.is_none() as u8 as f64);
+
+    if let Some(ref data) = state.data {
+        metrics.family("tincmgr_nodes", "gauge", "Nodes known to tincd.");
+        metrics.sample("tincmgr_nodes", &[], data.nodes.len() as f64);
+        metrics.family("tincmgr_nodes_reachable", "gauge", "Nodes currently reachable.");
+        metrics.sample("tincmgr_nodes_reachable", &[],
+                       data.nodes.iter().filter(|x| x.reachable == 1).count() as f64);
+        metrics.family("tincmgr_nodes_data_path", "gauge", "Peers by how the local daemon sends them packets.");
+        let paths = [
+            ("udp", DataPath::Udp),
+            ("tcp", DataPath::Tcp),
+            ("relayed", DataPath::Relayed),
+            ("pending", DataPath::Pending),
+        ];
+        for (label, path) in paths.iter() {
+            let count = data.nodes.iter().filter(|x| x.data_path == *path).count();
+            metrics.sample("tincmgr_nodes_data_path", &[("path", label)], count as f64);
+        }
+        metrics.family("tincmgr_meta_connections", "gauge", "Meta connections of the local daemon to other nodes.");
+        metrics.sample("tincmgr_meta_connections", &[],
+                       data.connections.iter().filter(|x| x.is_meta()).count() as f64);
+        metrics.family("tincmgr_links", "gauge", "Links between nodes, each direction pair counted once.");
+        metrics.sample("tincmgr_links", &[], data.links.len() as f64);
+
+        metrics.family("tincmgr_subnet_conflicts", "gauge", "Pairs of subnets advertised in conflict.");
+        let kinds = [
+            ("duplicate", ConflictKind::Duplicate),
+            ("weight_tie", ConflictKind::WeightTie),
+            ("shadowed", ConflictKind::Shadowed),
+            ("overlap", ConflictKind::Overlap),
+        ];
+        for (label, kind) in kinds.iter() {
+            metrics.sample("tincmgr_subnet_conflicts", &[("kind", label)], state.conflicts.count(kind) as f64);
+        }
+
+        metrics.family("tincmgr_audit_findings", "gauge", "Weak protocol or crypto settings of reachable nodes.");
+        for (label, severity) in [("warning", Severity::Warning), ("critical", Severity::Critical)].iter() {
+            metrics.sample("tincmgr_audit_findings", &[("severity", label)], state.audit.count(*severity) as f64);
+        }
+        metrics.family("tincmgr_nodes_protocol", "gauge", "Reachable nodes by announced protocol version.");
+        for version in state.audit.versions.iter() {
+            let minor = version.protocol_minor.to_string();
+            let sptps = if version.sptps { "1" } else { "0" };
+            metrics.sample("tincmgr_nodes_protocol", &[("minor", &minor), ("sptps", sptps)], version.nodes.len() as f64);
+        }
+    }
+
+    if let Some(ref process) = state.process {
+        let pid = process.pid.to_string();
+        let labels = [("pid", pid.as_str())];
+        metrics.family("tincd_cpu_seconds_total", "counter", "CPU time used by tincd.");
+        metrics.sample("tincd_cpu_seconds_total", &[("pid", &pid), ("mode", "user")], process.cpu_user_secs);
+        metrics.sample("tincd_cpu_seconds_total", &[("pid", &pid), ("mode", "system")], process.cpu_system_secs);
+        metrics.family("tincd_resident_memory_bytes", "gauge", "Resident memory of tincd.");
+        metrics.sample("tincd_resident_memory_bytes", &labels, process.rss_bytes as f64);
+        if let Some(open_fds) = process.open_fds {
+            metrics.family("tincd_open_fds", "gauge", "Open file descriptors of tincd.");
+            metrics.sample("tincd_open_fds", &labels, open_fds as f64);
+        }
+        metrics.family("tincd_threads", "gauge", "Threads of tincd.");
+        metrics.sample("tincd_threads", &labels, process.threads as f64);
+        metrics.family("tincd_uptime_seconds", "gauge", "Time since tincd started.");
+        metrics.sample("tincd_uptime_seconds", &labels, process.uptime_secs);
+    }
+
+    if state.routes.interface.is_some() {
+        metrics.family("tincmgr_route_problems", "gauge", "VPN subnets the kernel does not route into the VPN interface.");
+        for kind in ["missing", "wrong_interface", "shadowed"].iter() {
+            let count = state.routes.problems.iter()
+                .filter(|x| match x.kind {
+                    RouteProblemKind::Missing => *kind == "missing",
+                    RouteProblemKind::WrongInterface { .. } => *kind == "wrong_interface",
+                    RouteProblemKind::Shadowed { .. } => *kind == "shadowed",
+                })
+                .count();
+            metrics.sample("tincmgr_route_problems", &[("kind", kind)], count as f64);
+        }
+    }
+
+    if !state.pmtu.nodes.is_empty() {
+        metrics.family("tincmgr_node_pmtu", "gauge", "Path MTU tincd discovered towards a node.");
+        for node in state.pmtu.nodes.iter() {
+            metrics.sample("tincmgr_node_pmtu", &[("node", &node.name)], node.pmtu as f64);
+        }
+        metrics.family("tincmgr_node_pmtu_converged", "gauge", "Whether PMTU discovery towards a node has converged.");
+        for node in state.pmtu.nodes.iter() {
+            metrics.sample("tincmgr_node_pmtu_converged", &[("node", &node.name)], node.converged as u8 as f64);
+        }
+    }
+    metrics.family("tincmgr_pmtu_below_threshold", "gauge", "Nodes whose path MTU is below the configured minimum.");
+    metrics.sample("tincmgr_pmtu_below_threshold", &[], state.pmtu.below_threshold().count() as f64);
+    metrics.family("tincmgr_pmtu_unconverged", "gauge", "Nodes whose PMTU discovery has not converged.");
+    metrics.sample("tincmgr_pmtu_unconverged", &[], state.pmtu.unconverged().count() as f64);
+
+    if !state.endpoints.nodes.is_empty() {
+        metrics.family("tincmgr_nodes_behind_nat", "gauge", "Nodes seen at another address than they announce.");
+        metrics.sample("tincmgr_nodes_behind_nat", &[],
+                       state.endpoints.nodes.iter().filter(|x| x.behind_nat).count() as f64);
+        metrics.family("tincmgr_nodes_unknown_endpoint", "gauge", "Nodes without any known address.");
+        metrics.sample("tincmgr_nodes_unknown_endpoint", &[],
+                       state.endpoints.nodes.iter().filter(|x| x.unknown).count() as f64);
+    }
+
+    if !state.traffic.nodes.is_empty() {
+        metrics.family("tincmgr_node_traffic_bytes_total", "counter", "Bytes exchanged with a node, across tincd restarts.");
+        for (name, account) in state.traffic.nodes.iter() {
+            metrics.sample("tincmgr_node_traffic_bytes_total", &[("node", name), ("direction", "in")],
+                           account.total.in_bytes as f64);
+            metrics.sample("tincmgr_node_traffic_bytes_total", &[("node", name), ("direction", "out")],
+                           account.total.out_bytes as f64);
+        }
+    }
+
+    if !state.quotas.nodes.is_empty() {
+        metrics.family("tincmgr_node_quota_used_ratio", "gauge", "Traffic of the current day or month over the node's quota.");
+        for node in state.quotas.nodes.iter() {
+            for (period, usage) in [("day", &node.daily), ("month", &node.monthly)].iter() {
+                if let Some(usage) = usage {
+                    metrics.sample("tincmgr_node_quota_used_ratio", &[("node", &node.name), ("period", period)],
+                                   usage.percent / 100.0);
+                }
+            }
+        }
+        metrics.family("tincmgr_nodes_over_quota", "gauge", "Nodes that used up a daily or monthly quota.");
+        metrics.sample("tincmgr_nodes_over_quota", &[], state.quotas.over_quota().count() as f64);
+        metrics.family("tincmgr_nodes_over_rate", "gauge", "Nodes above their maximum rate.");
+        metrics.sample("tincmgr_nodes_over_rate", &[], state.quotas.nodes.iter().filter(|x| x.over_rate).count() as f64);
+    }
+
+    if state.allowlist.source.is_some() {
+        metrics.family("tincmgr_allowlist_nodes", "gauge", "Nodes on the allowlist, and nodes it disagrees with the mesh about.");
+        for (label, count) in [("expected", state.allowlist.expected), ("unknown", state.allowlist.unknown.len()),
+                               ("never_seen", state.allowlist.never_seen.len()),
+                               ("unreachable", state.allowlist.unreachable.len()),
+                               ("vanished", state.allowlist.vanished.len())].iter() {
+            metrics.sample("tincmgr_allowlist_nodes", &[("state", label)], *count as f64);
+        }
+        metrics.family("tincmgr_node_unknown", "gauge", "A node that is not on the allowlist.");
+        for name in state.allowlist.unknown.iter() {
+            metrics.sample("tincmgr_node_unknown", &[("node", name)], 1.0);
+        }
+    }
+
+    if !state.flaps.states.is_empty() {
+        metrics.family("tincmgr_flap_score", "gauge", "Reachability or link changes within the flap window.");
+        for x in state.flaps.states.iter() {
+            let subject = match x.subject {
+                FlapSubject::Node => "node",
+                FlapSubject::Link => "link",
+            };
+            metrics.sample("tincmgr_flap_score", &[("subject", subject), ("name", &x.name)], x.score as f64);
+        }
+    }
+    metrics.family("tincmgr_flapping", "gauge", "Nodes and links currently flapping.");
+    metrics.sample("tincmgr_flapping", &[], state.flaps.flapping().count() as f64);
+
+    if !state.latency.links.is_empty() {
+        metrics.family("tincmgr_link_rtt_milliseconds", "gauge", "Latest round trip time of a link.");
+        for link in state.latency.links.iter() {
+            metrics.sample("tincmgr_link_rtt_milliseconds",
+                           &[("sname", &link.sname), ("tname", &link.tname)], link.rtt);
+        }
+    }
+
+    if let Some(interface) = state.interface.latest() {
+        let labels = [("interface", interface.name.as_str())];
+        metrics.family("tincmgr_interface_mtu", "gauge", "MTU of the VPN interface.");
+        metrics.sample("tincmgr_interface_mtu", &labels, interface.mtu as f64);
+        let counters = [
+            ("tincmgr_interface_receive_bytes_total", "Bytes received by the VPN interface.", interface.rx_bytes),
+            ("tincmgr_interface_transmit_bytes_total", "Bytes sent by the VPN interface.", interface.tx_bytes),
+            ("tincmgr_interface_receive_packets_total", "Packets received by the VPN interface.", interface.rx_packets),
+            ("tincmgr_interface_transmit_packets_total", "Packets sent by the VPN interface.", interface.tx_packets),
+            ("tincmgr_interface_receive_errors_total", "Receive errors of the VPN interface.", interface.rx_errors),
+            ("tincmgr_interface_transmit_errors_total", "Transmit errors of the VPN interface.", interface.tx_errors),
+            ("tincmgr_interface_receive_drop_total", "Received packets dropped by the kernel.", interface.rx_dropped),
+            ("tincmgr_interface_transmit_drop_total", "Sent packets dropped by the kernel.", interface.tx_dropped),
+        ];
+        for (name, help, value) in counters.iter() {
+            metrics.family(name, "counter", help);
+            metrics.sample(name, &labels, *value as f64);
+        }
+    }
+
+    metrics.out
+}
+
+#[derive(Default)]
+struct Metrics {
+    out: String,
+}
+impl Metrics {
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        let _ = writeln!(self.out, "# HELP {} {}", name, help);
+        let _ = writeln!(self.out, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        let labels: Vec<String> = labels.iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, v.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(self.out, "{} {}", name, value);
+        } else {
+            let _ = writeln!(self.out, "{}{{{}}} {}", name, labels.join(","), value);
+        }
+    }
+}
@@ -0,0 +1,426 @@
+//! A fake tincd control server for hermetic tests.
+//!
+//! `MockTincd::start` listens on a loopback port, writes a pidfile in the
+//! same `<pid> <cookie> <address> port <port>` format tincd uses, naming a
+//! stand-in process called tincd, performs
+//! the control handshake and answers every `RequestType` from a scripted
+//! `Topology`, the same way tincd's `control_h` does.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Result, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+use crate::tinc_tcp_stream::{Request, RequestType};
+
+static MOCK_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Debug)]
+pub struct MockNode {
+    pub name:               String,
+    pub id:                 String,
+    pub host:               String,
+    pub port:               String,
+    pub cipher:             i32,
+    pub digest:             i32,
+    pub maclength:          i32,
+    pub compression:        i32,
+    pub options:            u32,
+    pub status:             u32,
+    pub nexthop:            String,
+    pub via:                String,
+    pub distance:           i32,
+    pub pmtu:               i32,
+    pub minmtu:             i32,
+    pub maxmtu:             i32,
+    pub last_state_change:  i64,
+}
+impl MockNode {
+    /// A reachable, directly connected SPTPS node.
+    pub fn new(name: &str) -> Self {
+        MockNode {
+            name:               name.to_string(),
+            id:                 "000000000000".to_string(),
+            host:               "unknown".to_string(),
+            port:               "unknown".to_string(),
+            cipher:             0,
+            digest:             0,
+            maclength:          0,
+            compression:        0,
+            options:            0x0700_000c,
+            status:             0x0000_0012,
+            nexthop:            name.to_string(),
+            via:                name.to_string(),
+            distance:           1,
+            pmtu:               1518,
+            minmtu:             1518,
+            maxmtu:             1518,
+            last_state_change:  0,
+        }
+    }
+
+    pub fn unreachable(mut self) -> Self {
+        self.status &= !0x10;
+        self.nexthop = "-".to_string();
+        self.via = "-".to_string();
+        self
+    }
+
+    fn line(&self) -> String {
+        format!("{} {} {} {} {} port {} {} {} {} {} {:x} {:x} {} {} {} {} {} {} {}",
+                Request::Control as i8, RequestType::ReqDumpNodes as i8,
+                self.name, self.id, self.host, self.port,
+                self.cipher, self.digest, self.maclength, self.compression,
+                self.options, self.status, self.nexthop, self.via, self.distance,
+                self.pmtu, self.minmtu, self.maxmtu, self.last_state_change)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockEdge {
+    pub from:           String,
+    pub to:             String,
+    pub host:           String,
+    pub port:           String,
+    pub local_host:     String,
+    pub local_port:     String,
+    pub options:        u32,
+    pub weight:         i32,
+    pub avg_rtt:        Option<i32>,
+}
+impl MockEdge {
+    pub fn new(from: &str, to: &str, weight: i32) -> Self {
+        MockEdge {
+            from:       from.to_string(),
+            to:         to.to_string(),
+            host:       "unknown".to_string(),
+            port:       "unknown".to_string(),
+            local_host: "unknown".to_string(),
+            local_port: "unknown".to_string(),
+            options:    0x0700_000c,
+            weight,
+            avg_rtt:    None,
+        }
+    }
+
+    fn line(&self) -> String {
+        let mut line = format!("{} {} {} {} {} port {} {} port {} {:x} {}",
+                               Request::Control as i8, RequestType::ReqDumpEdges as i8,
+                               self.from, self.to, self.host, self.port,
+                               self.local_host, self.local_port, self.options, self.weight);
+        if let Some(avg_rtt) = self.avg_rtt {
+            line += &format!(" {}", avg_rtt);
+        }
+        line
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MockConnection {
+    pub name:           String,
+    pub host:           String,
+    pub port:           String,
+    pub options:        u32,
+    pub socket:         i32,
+    pub status:         u32,
+}
+impl MockConnection {
+    pub fn new(name: &str, socket: i32) -> Self {
+        MockConnection {
+            name:       name.to_string(),
+            host:       "unknown".to_string(),
+            port:       "unknown".to_string(),
+            options:    0x0700_000c,
+            socket,
+            status:     0x0000_00c0,
+        }
+    }
+
+    fn line(&self) -> String {
+        format!("{} {} {} {} port {} {:x} {} {:x}",
+                Request::Control as i8, RequestType::ReqDumpConnections as i8,
+                self.name, self.host, self.port, self.options, self.socket, self.status)
+    }
+}
+
+/// How the mock misbehaves when answering one request type.
+#[derive(Clone, Debug)]
+pub enum Fault {
+    /// Wait before answering.
+    Delay(Duration),
+    /// Close the connection instead of answering.
+    Disconnect,
+}
+
+/// The scripted state the mock reports. Lines are kept fully rendered, so
+/// `raw` can inject malformed ones next to the generated ones.
+#[derive(Clone, Debug)]
+pub struct Topology {
+    pub name:           String,
+    pub protocol:       String,
+    pub ctl_version:    i32,
+    dumps:              HashMap<i8, Vec<String>>,
+    faults:             HashMap<i8, Fault>,
+}
+impl Topology {
+    pub fn new(name: &str) -> Self {
+        Topology {
+            name:           name.to_string(),
+            protocol:       "17.7".to_string(),
+            ctl_version:    0,
+            dumps:          HashMap::new(),
+            faults:         HashMap::new(),
+        }
+    }
+
+    pub fn node(self, node: MockNode) -> Self {
+        self.raw(RequestType::ReqDumpNodes, &node.line())
+    }
+
+    pub fn edge(self, edge: MockEdge) -> Self {
+        self.raw(RequestType::ReqDumpEdges, &edge.line())
+    }
+
+    pub fn subnet(self, addr: &str, owner: &str) -> Self {
+        let line = format!("{} {} {} {}",
+                           Request::Control as i8, RequestType::ReqDumpSubnets as i8, addr, owner);
+        self.raw(RequestType::ReqDumpSubnets, &line)
+    }
+
+    pub fn connection(self, connection: MockConnection) -> Self {
+        self.raw(RequestType::ReqDumpConnections, &connection.line())
+    }
+
+    pub fn traffic(self, name: &str, in_packets: u64, in_bytes: u64, out_packets: u64, out_bytes: u64) -> Self {
+        let line = format!("{} {} {} {} {} {} {}",
+                           Request::Control as i8, RequestType::ReqDumpTraffic as i8,
+                           name, in_packets, in_bytes, out_packets, out_bytes);
+        self.raw(RequestType::ReqDumpTraffic, &line)
+    }
+
+    /// Append `line` verbatim to the answer for `request`.
+    pub fn raw(mut self, request: RequestType, line: &str) -> Self {
+        self.dumps.entry(request as i8).or_default().push(line.to_string());
+        self
+    }
+
+    pub fn fault(mut self, request: RequestType, fault: Fault) -> Self {
+        self.faults.insert(request as i8, fault);
+        self
+    }
+}
+
+pub struct MockTincd {
+    pidfile:        PathBuf,
+    dir:            PathBuf,
+    daemon:         Child,
+    cookie:         String,
+    port:           u16,
+    topology:       Arc<Mutex<Topology>>,
+    requests:       Arc<Mutex<Vec<String>>>,
+    running:        Arc<AtomicBool>,
+}
+impl MockTincd {
+    pub fn start(topology: Topology) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        let count = MOCK_COUNT.fetch_add(1, Ordering::SeqCst);
+        let cookie = format!("{:016x}{:048x}", std::process::id(), count);
+        let dir = std::env::temp_dir().join(format!("tincmgr-mock-{}-{}", std::process::id(), count));
+        let daemon = start_daemon(&dir)?;
+        let pidfile = std::env::temp_dir()
+            .join(format!("tincmgr-mock-{}-{}.pid", std::process::id(), count));
+        fs::write(&pidfile, format!("{} {} 127.0.0.1 port {}\n", daemon.id(), cookie, port))?;
+
+        let pid = daemon.id();
+        let mock = MockTincd {
+            pidfile,
+            dir,
+            daemon,
+            cookie,
+            port,
+            topology: Arc::new(Mutex::new(topology)),
+            requests: Arc::new(Mutex::new(vec![])),
+            running: Arc::new(AtomicBool::new(true)),
+        };
+
+        let cookie = mock.cookie.clone();
+        let topology = mock.topology.clone();
+        let requests = mock.requests.clone();
+        let running = mock.running.clone();
+        spawn(move || {
+            for stream in listener.incoming() {
+                if !running.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let cookie = cookie.clone();
+                    let topology = topology.clone();
+                    let requests = requests.clone();
+                    spawn(move || serve(stream, &cookie, pid, &topology, &requests));
+                }
+            }
+        });
+        Ok(mock)
+    }
+
+    pub fn pidfile(&self) -> &Path {
+        &self.pidfile
+    }
+
+    pub fn pid_path(&self) -> &str {
+        self.pidfile.to_str().unwrap()
+    }
+
+    pub fn cookie(&self) -> &str {
+        &self.cookie
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// The pid of the stand-in tincd process the pidfile names.
+    pub fn pid(&self) -> u32 {
+        self.daemon.id()
+    }
+
+    /// Replace what the mock reports from the next request on.
+    pub fn set_topology(&self, topology: Topology) {
+        *self.topology.lock().unwrap() = topology;
+    }
+
+    /// Every control request received so far, as sent on the wire.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+impl Drop for MockTincd {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::SeqCst);
+        let _ = TcpStream::connect(("127.0.0.1", self.port));
+        let _ = fs::remove_file(&self.pidfile);
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Run a copy of `sleep` called tincd in `dir`, so the pidfile names a
+/// live tincd like the real one does.
+fn start_daemon(dir: &Path) -> Result<Child> {
+    let binary = ["/bin/sleep", "/usr/bin/sleep"].iter()
+        .find(|x| Path::new(x).exists())
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No sleep binary to stand in for tincd."))?;
+    fs::create_dir_all(dir)?;
+    let tincd = dir.join("tincd");
+    fs::copy(binary, &tincd)?;
+    let mut retries = 50;
+    loop {
+        match Command::new(&tincd).arg("86400").stdin(Stdio::null()).spawn() {
+            // A fork in another test thread may still hold the copy open
+            // for writing for a moment.
+            Err(ref e) if e.raw_os_error() == Some(26) && retries > 0 => {
+                retries -= 1;
+                sleep(Duration::from_millis(10));
+            }
+            result => return result,
+        }
+    }
+}
+
+fn serve(
+    stream:     TcpStream,
+    cookie:     &str,
+    pid:        u32,
+    topology:   &Mutex<Topology>,
+    requests:   &Mutex<Vec<String>>,
+) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    let mut lines = BufReader::new(stream).lines();
+
+    let (name, protocol, ctl_version) = {
+        let topology = topology.lock().unwrap();
+        (topology.name.clone(), topology.protocol.clone(), topology.ctl_version)
+    };
+    // Like tincd, greet only once the cookie checks out, and hang up
+    // silently otherwise.
+    let id = match lines.next() {
+        Some(Ok(id)) => id,
+        _ => return,
+    };
+    let id: Vec<&str> = id.split_whitespace().collect();
+    if id.len() < 2 || id[0] != "0" || id[1] != format!("^{}", cookie) {
+        let _ = writer.shutdown(Shutdown::Both);
+        return;
+    }
+    if writeln!(writer, "{} {} {}", Request::Id as i8, name, protocol).is_err()
+        || writeln!(writer, "{} {} {}", Request::Ack as i8, ctl_version, pid).is_err() {
+        return;
+    }
+
+    for line in lines {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        requests.lock().unwrap().push(line.clone());
+        let request: Vec<i8> = line.split_whitespace().filter_map(|x| x.parse().ok()).collect();
+        if request.len() < 2 || request[0] != Request::Control as i8 {
+            return;
+        }
+        let fault = topology.lock().unwrap().faults.get(&request[1]).cloned();
+        match fault {
+            Some(Fault::Disconnect) => {
+                let _ = writer.shutdown(Shutdown::Both);
+                return;
+            }
+            Some(Fault::Delay(delay)) => sleep(delay),
+            None => (),
+        }
+        let answer = answer(&topology.lock().unwrap(), request[1]);
+        if let Some(answer) = answer {
+            if writer.write_all(answer.as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+fn answer(topology: &Topology, request: i8) -> Option<String> {
+    let control = Request::Control as i8;
+    let dump = |request: i8| {
+        let mut answer = String::new();
+        for line in topology.dumps.get(&request).into_iter().flatten() {
+            answer += line;
+            answer += "\n";
+        }
+        answer + &format!("{} {}\n", control, request)
+    };
+    match request {
+        x if x == RequestType::ReqStop as i8
+            || x == RequestType::ReqReload as i8
+            || x == RequestType::ReqPurge as i8
+            || x == RequestType::ReqRetry as i8
+            || x == RequestType::ReqDisconnect as i8 => Some(format!("{} {} 0\n", control, x)),
+        x if x == RequestType::ReqDumpNodes as i8
+            || x == RequestType::ReqDumpEdges as i8
+            || x == RequestType::ReqDumpSubnets as i8
+            || x == RequestType::ReqDumpConnections as i8
+            || x == RequestType::ReqDumpTraffic as i8 => Some(dump(x)),
+        x if x == RequestType::ReqSetDebug as i8 => Some(format!("{} {} 0\n", control, x)),
+        // tincd starts streaming packets or log lines here, never a reply.
+        x if x == RequestType::ReqPcap as i8
+            || x == RequestType::ReqLog as i8 => None,
+        _ => Some(format!("{} {}\n", control, RequestType::ReqInvalid as i8)),
+    }
+}
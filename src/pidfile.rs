@@ -0,0 +1,121 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread::spawn;
+
+use inotify::{Inotify, WatchMask};
+
+const TINCD_NAME: &str = "tincd";
+
+/// The `<pid> <cookie> <address> port <port>` line tincd writes on start.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PidFile {
+    pub pid:        i32,
+    pub cookie:     String,
+    pub address:    String,
+    pub port:       String,
+}
+impl PidFile {
+    pub fn read(path: &str) -> Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        let iter: Vec<&str> = contents.split_whitespace().collect();
+        if iter.len() < 5 {
+            error!("Tinc pid file, not find port setting. Maybe tinc tcp port never be set");
+            return Err(Error::new(ErrorKind::InvalidData, "Tinc pid file, not find port setting. Maybe tinc tcp port never be set"));
+        }
+        let pid = iter[0].parse()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Tinc pid file, invalid pid."))?;
+        Ok(PidFile {
+            pid,
+            cookie: iter[1].to_string(),
+            address: iter[2].to_string(),
+            port: iter[4].to_string(),
+        })
+    }
+
+    pub fn daemon_state(&self) -> DaemonState {
+        if !Path::new(&format!("/proc/{}", self.pid)).exists() {
+            return DaemonState::NotRunning;
+        }
+        match process_name(self.pid) {
+            Some(ref name) if name != TINCD_NAME => DaemonState::NotTincd(name.clone()),
+            _ => DaemonState::Running,
+        }
+    }
+
+    /// Turn a failure to reach or attach to the control port into an
+    /// error that tells a stale pidfile apart from a problem of the live
+    /// tincd, which is returned as is.
+    pub fn diagnose(&self, path: &str, err: Error) -> Error {
+        match self.daemon_state() {
+            DaemonState::NotRunning => Error::new(
+                ErrorKind::NotFound,
+                format!("Stale pid file {}: tincd pid {} is not running.", path, self.pid)),
+            DaemonState::NotTincd(name) => Error::new(
+                ErrorKind::NotFound,
+                format!("Stale pid file {}: pid {} is {}, not tincd.", path, self.pid, name)),
+            DaemonState::Running => err,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DaemonState {
+    Running,
+    NotRunning,
+    /// The pid has been reused by another program.
+    NotTincd(String),
+}
+
+/// The executable name of `pid`, from `/proc/<pid>/exe` or, when that link
+/// is not readable, from the first word of `/proc/<pid>/cmdline`.
+pub fn process_name(pid: i32) -> Option<String> {
+    if let Ok(exe) = fs::read_link(format!("/proc/{}/exe", pid)) {
+        if let Some(name) = exe.file_name() {
+            let name = name.to_string_lossy();
+            return Some(name.trim_end_matches(" (deleted)").to_string());
+        }
+    }
+    let cmdline = fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let argv0 = cmdline.split(|x| *x == 0).next()?;
+    let name = Path::new(OsStr::from_bytes(argv0)).file_name()?;
+    Some(name.to_string_lossy().to_string())
+}
+
+/// Send a message every time the pidfile is written, replaced or removed.
+/// The parent directory is watched since tincd recreates the file.
+pub fn watch(path: &str) -> Result<Receiver<()>> {
+    let path = Path::new(path);
+    let dir = match path.parent() {
+        Some(dir) if dir != Path::new("") => dir.to_path_buf(),
+        _ => Path::new(".").to_path_buf(),
+    };
+    let file_name = path.file_name()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Tinc pid file, invalid path."))?
+        .to_os_string();
+
+    let mut inotify = Inotify::init()?;
+    inotify.add_watch(&dir, WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::DELETE)?;
+
+    let (tx, rx) = channel();
+    spawn(move || {
+        let mut buffer = [0; 1024];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(e) => {
+                    error!("Watch pid file failed: {:?}", e);
+                    return;
+                }
+            };
+            if events.filter(|event| event.name == Some(file_name.as_os_str())).count() > 0
+                && tx.send(()).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(rx)
+}
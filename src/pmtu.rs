@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::domain::Data;
+
+const PMTU_HISTORY_LEN: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodePmtu {
+    pub name:               String,
+    pub pmtu:               u16,
+    pub minmtu:             u16,
+    pub maxmtu:             u16,
+    /// Discovery is no longer probing between `minmtu` and `maxmtu`.
+    pub converged:          bool,
+    pub below_threshold:    bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PmtuChange {
+    pub time:       i64,
+    pub name:       String,
+    pub from:       u16,
+    pub to:         u16,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PmtuReport {
+    pub threshold:  u16,
+    pub nodes:      Vec<NodePmtu>,
+    pub changes:    Vec<PmtuChange>,
+}
+impl PmtuReport {
+    pub fn unconverged(&self) -> impl Iterator<Item = &NodePmtu> {
+        self.nodes.iter().filter(|x| !x.converged)
+    }
+
+    pub fn below_threshold(&self) -> impl Iterator<Item = &NodePmtu> {
+        self.nodes.iter().filter(|x| x.below_threshold)
+    }
+}
+
+/// Follows the path MTU tincd discovered for every reachable peer that
+/// does PMTU discovery, and remembers when it changes.
+pub struct PmtuMonitor {
+    threshold:      u16,
+    last:           HashMap<String, u16>,
+    changes:        Vec<PmtuChange>,
+}
+impl PmtuMonitor {
+    pub fn new(threshold: u16) -> Self {
+        PmtuMonitor {
+            threshold,
+            last: HashMap::new(),
+            changes: vec![],
+        }
+    }
+
+    pub fn update(&mut self, data: &Data, time: i64) -> PmtuReport {
+        let mut nodes = vec![];
+        for node in data.nodes.iter() {
+            if node.local || node.reachable == 0 || node.options.tcponly || !node.options.pmtu_discovery {
+                continue;
+            }
+            if let Some(from) = self.last.insert(node.name.clone(), node.pmtu) {
+                if from != node.pmtu {
+                    info!("PMTU of {} changed from {} to {}", node.name, from, node.pmtu);
+                    if self.changes.len() >= PMTU_HISTORY_LEN {
+                        self.changes.remove(0);
+                    }
+                    self.changes.push(PmtuChange {
+                        time,
+                        name: node.name.clone(),
+                        from,
+                        to: node.pmtu,
+                    });
+                }
+            }
+            nodes.push(NodePmtu {
+                name: node.name.clone(),
+                pmtu: node.pmtu,
+                minmtu: node.minmtu,
+                maxmtu: node.maxmtu,
+                converged: node.minmtu == node.maxmtu,
+                below_threshold: node.minmtu == node.maxmtu && node.pmtu < self.threshold,
+            });
+        }
+        PmtuReport {
+            threshold: self.threshold,
+            nodes,
+            changes: self.changes.clone(),
+        }
+    }
+}
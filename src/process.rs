@@ -0,0 +1,81 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+
+// USER_HZ, the unit of the tick counters in /proc/<pid>/stat.
+const CLOCK_TICKS: f64 = 100.0;
+
+/// Resource usage of the tincd process, read from `/proc/<pid>/`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProcessStats {
+    pub pid:                i32,
+    pub cpu_user_secs:      f64,
+    pub cpu_system_secs:    f64,
+    pub cpu_percent:        Option<f64>,
+    pub rss_bytes:          u64,
+    pub open_fds:           Option<u64>,
+    pub threads:            u64,
+    pub uptime_secs:        f64,
+    pub sampled_at:         f64,
+}
+impl ProcessStats {
+    /// Sample `pid`. With the `previous` sample of the same process the CPU
+    /// usage since then is filled in as well.
+    pub fn sample(pid: i32, previous: Option<&ProcessStats>) -> Result<Self> {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid))?;
+        let status = fs::read_to_string(format!("/proc/{}/status", pid))?;
+        let system_uptime = fs::read_to_string("/proc/uptime")?;
+
+        // The command name is in parentheses and may contain spaces.
+        let fields: Vec<&str> = match stat.rfind(')') {
+            Some(x) => stat[x + 1..].split_whitespace().collect(),
+            None => vec![],
+        };
+        if fields.len() < 20 {
+            return Err(Error::new(ErrorKind::InvalidData, "Parse /proc/<pid>/stat failed."));
+        }
+        let ticks = |i: usize| fields[i].parse::<f64>().unwrap_or(0.0) / CLOCK_TICKS;
+        // fields[i] is field i + 3 in proc(5).
+        let cpu_user_secs = ticks(11);
+        let cpu_system_secs = ticks(12);
+        let start_secs = ticks(19);
+
+        let sampled_at: f64 = system_uptime.split_whitespace().next()
+            .and_then(|x| x.parse().ok())
+            .unwrap_or(0.0);
+
+        let open_fds = fs::read_dir(format!("/proc/{}/fd", pid)).ok()
+            .map(|fds| fds.count() as u64);
+
+        let mut stats = ProcessStats {
+            pid,
+            cpu_user_secs,
+            cpu_system_secs,
+            cpu_percent: None,
+            rss_bytes: status_value(&status, "VmRSS:") * 1024,
+            open_fds,
+            threads: status_value(&status, "Threads:"),
+            uptime_secs: sampled_at - start_secs,
+            sampled_at,
+        };
+        if let Some(previous) = previous.filter(|x| x.pid == pid) {
+            let wall = stats.sampled_at - previous.sampled_at;
+            if wall > 0.0 {
+                let cpu = stats.cpu_secs() - previous.cpu_secs();
+                stats.cpu_percent = Some(cpu * 100.0 / wall);
+            }
+        }
+        Ok(stats)
+    }
+
+    pub fn cpu_secs(&self) -> f64 {
+        self.cpu_user_secs + self.cpu_system_secs
+    }
+}
+
+fn status_value(status: &str, key: &str) -> u64 {
+    status.lines()
+        .find(|line| line.starts_with(key))
+        .and_then(|line| line[key.len()..].split_whitespace().next())
+        .and_then(|x| x.parse().ok())
+        .unwrap_or(0)
+}
@@ -0,0 +1,138 @@
+use std::io::Result;
+use std::time::{Duration, Instant};
+
+use crate::control;
+use crate::domain::Data;
+use crate::domain::subnets::Subnet;
+
+const PURGE_HISTORY_LEN: usize = 100;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PurgePolicy {
+    Disabled,
+    Every(Duration),
+}
+impl PurgePolicy {
+    /// `never`/`off` disables purging, a number of seconds schedules it.
+    pub fn parse(policy: &str) -> Option<Self> {
+        match policy {
+            "never" | "off" | "0" => Some(PurgePolicy::Disabled),
+            _ => policy.parse().ok().map(|secs| PurgePolicy::Every(Duration::from_secs(secs))),
+        }
+    }
+
+    fn interval_secs(&self) -> Option<u64> {
+        match self {
+            PurgePolicy::Disabled => None,
+            PurgePolicy::Every(interval) => Some(interval.as_secs()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PurgedEdge {
+    pub from:       String,
+    pub to:         String,
+}
+
+/// What a `ReqPurge` would remove from tincd: the subnets and edges of
+/// every unreachable node, and those of the nodes no reachable node still
+/// has an edge to.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PurgeReport {
+    pub nodes:      Vec<String>,
+    pub subnets:    Vec<Subnet>,
+    pub edges:      Vec<PurgedEdge>,
+}
+impl PurgeReport {
+    pub fn from(data: &Data) -> Self {
+        let reachable = |name: &str| data.nodes.iter().any(|x| x.name == name && x.reachable == 1);
+        let mut edges = vec![];
+        for link in data.links.iter() {
+            if link.forward.is_some() {
+                edges.push((&link.sname, &link.tname));
+            }
+            if link.reverse.is_some() {
+                edges.push((&link.tname, &link.sname));
+            }
+        }
+
+        let mut report = PurgeReport::default();
+        for (from, to) in edges.iter().filter(|(from, _)| !reachable(from)) {
+            report.edges.push(PurgedEdge { from: from.to_string(), to: to.to_string() });
+        }
+        for node in data.nodes.iter().filter(|node| node.reachable == 0) {
+            report.subnets.extend(node.nets.iter().cloned());
+            // Edges of reachable nodes survive the purge and keep it known.
+            if !edges.iter().any(|(from, to)| **to == node.name && reachable(from)) {
+                report.nodes.push(node.name.clone());
+            }
+        }
+        report
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty() && self.subnets.is_empty() && self.edges.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PurgeRecord {
+    pub time:       i64,
+    pub purged:     PurgeReport,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PurgeStatus {
+    pub interval:   Option<u64>,
+    pub dry_run:    PurgeReport,
+    pub history:    Vec<PurgeRecord>,
+}
+
+pub struct Purger {
+    policy:         PurgePolicy,
+    last_run:       Instant,
+    history:        Vec<PurgeRecord>,
+}
+impl Purger {
+    pub fn new(policy: PurgePolicy) -> Self {
+        Purger {
+            policy,
+            last_run: Instant::now(),
+            history: vec![],
+        }
+    }
+
+    fn due(&self) -> bool {
+        match self.policy {
+            PurgePolicy::Disabled => false,
+            PurgePolicy::Every(interval) => self.last_run.elapsed() >= interval,
+        }
+    }
+
+    /// Purge tincd if the policy says it is time to, and return the status
+    /// to publish: what would be purged now, and what has been purged.
+    pub fn run(&mut self, pid_path: &str, data: &Data) -> Result<PurgeStatus> {
+        let mut report = PurgeReport::from(data);
+        if self.due() {
+            self.last_run = Instant::now();
+            if !report.is_empty() {
+                control::purge(pid_path)?;
+                info!("Purged nodes {:?}, subnets {:?}, edges {:?}", report.nodes, report.subnets, report.edges);
+                if self.history.len() >= PURGE_HISTORY_LEN {
+                    self.history.remove(0);
+                }
+                self.history.push(PurgeRecord {
+                    time: chrono::Local::now().timestamp(),
+                    purged: report,
+                });
+                report = PurgeReport::default();
+            }
+        }
+        Ok(PurgeStatus {
+            interval: self.policy.interval_secs(),
+            dry_run: report,
+            history: self.history.clone(),
+        })
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use crate::accounting::{Period, TrafficAccounting};
+use crate::domain::Data;
+use crate::domain::nodes::Traffic;
+use crate::events::{Event, EventKind};
+use crate::tinc_conf;
+
+pub const DEFAULT_WARN_LEVELS: [u8; 3] = [80, 90, 100];
+
+/// Limits in bytes, and bytes per second for the rate.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Quota {
+    pub daily:      Option<u64>,
+    pub monthly:    Option<u64>,
+    pub max_rate:   Option<u64>,
+}
+impl Quota {
+    fn or(&self, default: &Quota) -> Quota {
+        Quota {
+            daily: self.daily.or(default.daily),
+            monthly: self.monthly.or(default.monthly),
+            max_rate: self.max_rate.or(default.max_rate),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.daily.is_none() && self.monthly.is_none() && self.max_rate.is_none()
+    }
+}
+
+/// Quotas in tinc config syntax. Variables before the first `Node` apply
+/// to every node, the ones after a `Node = <name>` line to that node only:
+///
+/// ```text
+/// WarnAt = 80 90 100
+/// MonthlyQuota = 100G
+/// Node = branch1
+/// DailyQuota = 2G
+/// MaxRate = 1M
+/// ```
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    pub warn_levels:    Vec<u8>,
+    pub default:        Quota,
+    pub nodes:          BTreeMap<String, Quota>,
+}
+impl Default for QuotaConfig {
+    fn default() -> Self {
+        QuotaConfig {
+            warn_levels: DEFAULT_WARN_LEVELS.to_vec(),
+            default: Quota::default(),
+            nodes: BTreeMap::new(),
+        }
+    }
+}
+impl QuotaConfig {
+    pub fn parse(contents: &str) -> Result<Self> {
+        let invalid = |key: &str, value: &str| Error::new(ErrorKind::InvalidData,
+                                                          format!("Invalid {}: {}", key, value));
+        let mut config = QuotaConfig::default();
+        let mut node: Option<String> = None;
+        for (key, value) in tinc_conf::parse_variables(contents) {
+            if key == "node" {
+                config.nodes.entry(value.clone()).or_default();
+                node = Some(value);
+                continue;
+            }
+            if key == "warnat" {
+                let levels: Option<Vec<u8>> = value.split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|x| !x.is_empty())
+                    .map(|x| x.trim_end_matches('%').parse().ok())
+                    .collect();
+                config.warn_levels = levels.ok_or_else(|| invalid(&key, &value))?;
+                config.warn_levels.sort();
+                continue;
+            }
+            let quota = match node {
+                Some(ref node) => config.nodes.entry(node.clone()).or_default(),
+                None => &mut config.default,
+            };
+            let bytes = parse_bytes(&value).ok_or_else(|| invalid(&key, &value))?;
+            match key.as_str() {
+                "dailyquota" => quota.daily = Some(bytes),
+                "monthlyquota" => quota.monthly = Some(bytes),
+                "maxrate" => quota.max_rate = Some(bytes),
+                _ => return Err(Error::new(ErrorKind::InvalidData, format!("Unknown variable {}", key))),
+            }
+        }
+        Ok(config)
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn quota(&self, name: &str) -> Quota {
+        match self.nodes.get(name) {
+            Some(quota) => quota.or(&self.default),
+            None => self.default.clone(),
+        }
+    }
+}
+
+/// `1500`, `10K`, `2M`, `1.5G` or `1T`, in powers of 1024.
+pub fn parse_bytes(bytes: &str) -> Option<u64> {
+    let bytes = bytes.trim();
+    let (number, unit) = match bytes.chars().last()?.to_ascii_uppercase() {
+        'K' => (&bytes[..bytes.len() - 1], 1u64 << 10),
+        'M' => (&bytes[..bytes.len() - 1], 1 << 20),
+        'G' => (&bytes[..bytes.len() - 1], 1 << 30),
+        'T' => (&bytes[..bytes.len() - 1], 1 << 40),
+        _ => (bytes, 1),
+    };
+    let number: f64 = number.parse().ok().filter(|x: &f64| *x >= 0.0)?;
+    Some((number * unit as f64) as u64)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub limit:      u64,
+    pub used:       u64,
+    pub percent:    f64,
+    /// Highest warning level reached.
+    pub level:      Option<u8>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct NodeQuota {
+    pub name:       String,
+    pub daily:      Option<QuotaUsage>,
+    pub monthly:    Option<QuotaUsage>,
+    /// Bytes per second in both directions since the previous poll.
+    pub rate:       Option<f64>,
+    pub max_rate:   Option<u64>,
+    pub over_rate:  bool,
+    pub over_quota: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct QuotaReport {
+    pub warn_levels:    Vec<u8>,
+    pub nodes:          Vec<NodeQuota>,
+}
+impl QuotaReport {
+    pub fn over_quota(&self) -> impl Iterator<Item = &NodeQuota> {
+        self.nodes.iter().filter(|x| x.over_quota)
+    }
+}
+
+/// Compares the accounted traffic of every node with its quota, and warns
+/// once per day or month as usage passes each warning level. Levels passed
+/// before the first update, like before a tincmgr restart, are not warned
+/// about again.
+pub struct QuotaMonitor {
+    config:         QuotaConfig,
+    /// Level reached per node and day or month key.
+    levels:         HashMap<(String, Period), (String, u8)>,
+    counters:       HashMap<String, (i64, Traffic)>,
+    over_rate:      HashMap<String, bool>,
+    seeded:         bool,
+}
+impl QuotaMonitor {
+    pub fn new(config: QuotaConfig) -> Self {
+        QuotaMonitor {
+            config,
+            levels: HashMap::new(),
+            counters: HashMap::new(),
+            over_rate: HashMap::new(),
+            seeded: false,
+        }
+    }
+
+    pub fn update(&mut self, data: &Data, accounting: &TrafficAccounting, time: i64) -> (QuotaReport, Vec<Event>) {
+        let mut nodes = vec![];
+        let mut events = vec![];
+        let seeded = self.seeded;
+        self.seeded = true;
+        for node in data.nodes.iter().filter(|x| !x.local) {
+            let quota = self.config.quota(&node.name);
+            if quota.is_empty() {
+                continue;
+            }
+            let account = accounting.nodes.get(&node.name);
+            let mut usage = |period: Period, limit: Option<u64>| -> Option<QuotaUsage> {
+                let limit = limit?;
+                let key = period.key(time);
+                let used = account
+                    .and_then(|x| match period {
+                        Period::Day => x.days.get(&key),
+                        _ => x.months.get(&key),
+                    })
+                    .map_or(0, |x| x.bytes());
+                let percent = if limit > 0 { 100.0 * used as f64 / limit as f64 } else { 100.0 };
+                let level = self.config.warn_levels.iter().rev().find(|x| percent >= **x as f64).cloned();
+                let entry = self.levels.entry((node.name.clone(), period)).or_insert((key.clone(), 0));
+                if entry.0 != key {
+                    *entry = (key, 0);
+                }
+                if let Some(level) = level {
+                    if level > entry.1 {
+                        entry.1 = level;
+                        if seeded {
+                            warn!("{} used {}% of its {:?} quota", node.name, level, period);
+                            events.push(Event {
+                                time,
+                                node: node.name.clone(),
+                                kind: EventKind::QuotaWarning { period, level, used, limit },
+                            });
+                        }
+                    }
+                }
+                Some(QuotaUsage { limit, used, percent, level })
+            };
+            let daily = usage(Period::Day, quota.daily);
+            let monthly = usage(Period::Month, quota.monthly);
+
+            let rate = match (node.traffic.as_ref(), self.counters.get(&node.name)) {
+                (Some(traffic), Some((last_time, last))) if time > *last_time => traffic.since(last)
+                    .map(|x| x.bytes() as f64 / (time - last_time) as f64),
+                _ => None,
+            };
+            if let Some(ref traffic) = node.traffic {
+                self.counters.insert(node.name.clone(), (time, traffic.clone()));
+            }
+            let over_rate = match (rate, quota.max_rate) {
+                (Some(rate), Some(max_rate)) => rate > max_rate as f64,
+                _ => false,
+            };
+            let was_over_rate = self.over_rate.insert(node.name.clone(), over_rate).unwrap_or(false);
+            if over_rate && !was_over_rate {
+                let rate = rate.unwrap_or(0.0) as u64;
+                let limit = quota.max_rate.unwrap_or(0);
+                warn!("{} exceeds its rate limit: {} > {} bytes/s", node.name, rate, limit);
+                events.push(Event {
+                    time,
+                    node: node.name.clone(),
+                    kind: EventKind::RateExceeded { rate, limit },
+                });
+            }
+
+            let over_quota = [&daily, &monthly].iter()
+                .any(|x| x.as_ref().is_some_and(|x| x.used >= x.limit));
+            nodes.push(NodeQuota {
+                name: node.name.clone(),
+                daily,
+                monthly,
+                rate,
+                max_rate: quota.max_rate,
+                over_rate,
+                over_quota,
+            });
+        }
+        let report = QuotaReport {
+            warn_levels: self.config.warn_levels.clone(),
+            nodes,
+        };
+        (report, events)
+    }
+}
@@ -0,0 +1,143 @@
+use std::fs;
+use std::io::Result;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::Path;
+
+use crate::domain::Data;
+use crate::domain::subnets::{Net, Subnet};
+
+pub const PROC_NET: &str = "/proc/net";
+
+// RTF_UP and RTF_REJECT from linux/route.h.
+const RTF_UP: u32 = 0x0001;
+const RTF_REJECT: u32 = 0x0200;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct KernelRoute {
+    pub interface:      String,
+    pub destination:    IpAddr,
+    pub prefix:         u8,
+}
+impl KernelRoute {
+    fn net(&self) -> Net {
+        match self.destination {
+            IpAddr::V4(address) => Net::Ipv4 { address, prefix: self.prefix },
+            IpAddr::V6(address) => Net::Ipv6 { address, prefix: self.prefix },
+        }
+    }
+}
+
+/// Routes of the main table from `/proc/net/route` and `ipv6_route`, or the
+/// same files under `proc_net`. A missing file just contributes no routes.
+pub fn read_kernel_routes(proc_net: &Path) -> Result<Vec<KernelRoute>> {
+    let mut routes = vec![];
+    if let Ok(route) = fs::read_to_string(proc_net.join("route")) {
+        for line in route.lines().skip(1) {
+            let iter: Vec<&str> = line.split_whitespace().collect();
+            if iter.len() < 8 {
+                continue;
+            }
+            let flags = u32::from_str_radix(iter[3], 16).unwrap_or(0);
+            if flags & RTF_UP == 0 || flags & RTF_REJECT != 0 {
+                continue;
+            }
+            // Addresses are printed as host-order hex of network-order words.
+            let destination = u32::from_str_radix(iter[1], 16).unwrap_or(0);
+            let mask = u32::from_str_radix(iter[7], 16).unwrap_or(0);
+            routes.push(KernelRoute {
+                interface: iter[0].to_string(),
+                destination: IpAddr::V4(Ipv4Addr::from(u32::from_be(destination))),
+                prefix: mask.count_ones() as u8,
+            });
+        }
+    }
+    if let Ok(route) = fs::read_to_string(proc_net.join("ipv6_route")) {
+        for line in route.lines() {
+            let iter: Vec<&str> = line.split_whitespace().collect();
+            if iter.len() < 10 {
+                continue;
+            }
+            let flags = u32::from_str_radix(iter[8], 16).unwrap_or(0);
+            if flags & RTF_UP == 0 || flags & RTF_REJECT != 0 || iter[9] == "lo" {
+                continue;
+            }
+            let destination = match u128::from_str_radix(iter[0], 16) {
+                Ok(x) => x,
+                Err(_) => continue,
+            };
+            routes.push(KernelRoute {
+                interface: iter[9].to_string(),
+                destination: IpAddr::V6(Ipv6Addr::from(destination)),
+                prefix: u8::from_str_radix(iter[1], 16).unwrap_or(0),
+            });
+        }
+    }
+    Ok(routes)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RouteProblemKind {
+    /// No route but the default one covers the subnet.
+    Missing,
+    /// The most specific route for the subnet leaves through another interface.
+    WrongInterface { route: KernelRoute },
+    /// A more specific route on another interface takes part of the subnet.
+    Shadowed { route: KernelRoute },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RouteProblem {
+    pub subnet:         Subnet,
+    #[serde(flatten)]
+    pub kind:           RouteProblemKind,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RouteReport {
+    pub interface:      Option<String>,
+    pub checked:        usize,
+    pub problems:       Vec<RouteProblem>,
+}
+
+/// Check that every IP subnet owned by another reachable node is routed into
+/// the VPN interface. The subnet-down scripts remove the routes of nodes that
+/// become unreachable, so those are left out.
+pub fn check_routes(data: &Data, interface: &str, routes: &[KernelRoute]) -> RouteReport {
+    let mut report = RouteReport {
+        interface: Some(interface.to_string()),
+        ..RouteReport::default()
+    };
+    for node in data.nodes.iter().filter(|node| !node.local && node.reachable == 1) {
+        for subnet in node.nets.iter().filter(|subnet| subnet.net.ip().is_some()) {
+            report.checked += 1;
+            let problem = |kind| RouteProblem {
+                subnet: subnet.clone(),
+                kind,
+            };
+
+            let best = routes.iter()
+                .filter(|route| route.net().contains(&subnet.net))
+                .max_by_key(|route| route.prefix);
+            match best {
+                None => report.problems.push(problem(RouteProblemKind::Missing)),
+                Some(route) if route.interface != interface => {
+                    if route.prefix == 0 {
+                        report.problems.push(problem(RouteProblemKind::Missing));
+                    } else {
+                        report.problems.push(problem(RouteProblemKind::WrongInterface { route: route.clone() }));
+                    }
+                }
+                Some(_) => (),
+            }
+
+            for route in routes.iter() {
+                if route.interface != interface && route.prefix > subnet.net.prefix()
+                    && subnet.net.contains(&route.net()) {
+                    report.problems.push(problem(RouteProblemKind::Shadowed { route: route.clone() }));
+                }
+            }
+        }
+    }
+    report
+}
@@ -0,0 +1,41 @@
+use std::sync::{Arc, RwLock};
+
+use crate::accounting::TrafficAccounting;
+use crate::allowlist::AllowlistReport;
+use crate::domain::Data;
+use crate::domain::audit::AuditReport;
+use crate::domain::conflicts::ConflictReport;
+use crate::endpoints::EndpointReport;
+use crate::events::EventLog;
+use crate::flaps::FlapReport;
+use crate::interface::InterfaceSeries;
+use crate::latency::LatencyReport;
+use crate::pmtu::PmtuReport;
+use crate::process::ProcessStats;
+use crate::quotas::QuotaReport;
+use crate::purge::PurgeStatus;
+use crate::routes::RouteReport;
+use crate::tsdb::Tsdb;
+
+#[derive(Default)]
+pub struct State {
+    pub data:           Option<Data>,
+    pub attach_error:   Option<String>,
+    pub purge:          PurgeStatus,
+    pub process:        Option<ProcessStats>,
+    pub interface:      InterfaceSeries,
+    pub routes:         RouteReport,
+    pub conflicts:      ConflictReport,
+    pub audit:          AuditReport,
+    pub pmtu:           PmtuReport,
+    pub endpoints:      EndpointReport,
+    pub latency:        LatencyReport,
+    pub events:         EventLog,
+    pub flaps:          FlapReport,
+    pub series:         Tsdb,
+    pub traffic:        TrafficAccounting,
+    pub quotas:         QuotaReport,
+    pub allowlist:      AllowlistReport,
+}
+
+pub type SharedState = Arc<RwLock<State>>;
@@ -0,0 +1,50 @@
+use std::fs;
+use std::io::Result;
+use std::path::{Path, PathBuf};
+
+pub const CONFDIR: &str = "/etc/tinc";
+
+/// The netname in a pidfile named like tincd's `tinc.<netname>.pid`.
+pub fn netname(pidfile: &Path) -> Option<String> {
+    let name = pidfile.file_name()?.to_str()?;
+    let net = name.strip_prefix("tinc.")?.strip_suffix(".pid")?;
+    if net.is_empty() { None } else { Some(net.to_string()) }
+}
+
+/// Where tincd reads the configuration of `netname` from.
+pub fn confdir(netname: Option<&str>) -> PathBuf {
+    match netname {
+        Some(net) => Path::new(CONFDIR).join(net),
+        None => PathBuf::from(CONFDIR),
+    }
+}
+
+/// `Variable = Value` pairs of a tinc config file, in file order. Variable
+/// names are case insensitive in tinc, so they are returned lowercased.
+pub fn parse_variables(contents: &str) -> Vec<(String, String)> {
+    let mut variables = vec![];
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        // Stop at the public key blocks host files carry.
+        if line.starts_with("-----BEGIN") {
+            break;
+        }
+        let split = line.find(|c: char| c == '=' || c.is_whitespace()).unwrap_or(line.len());
+        let (key, value) = line.split_at(split);
+        let value = value.trim_start_matches(|c: char| c == '=' || c.is_whitespace());
+        variables.push((key.to_lowercase(), value.trim().to_string()));
+    }
+    variables
+}
+
+/// The first value of `key` in the config file at `path`.
+pub fn read_variable(path: &Path, key: &str) -> Result<Option<String>> {
+    let contents = fs::read_to_string(path)?;
+    let key = key.to_lowercase();
+    Ok(parse_variables(&contents).into_iter()
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| v))
+}
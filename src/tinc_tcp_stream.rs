@@ -1,11 +1,12 @@
 use std::net::TcpStream;
 use std::io::Write;
 use std::net::SocketAddr;
-use std::fs::File;
 use std::io::{Error, ErrorKind, Result, Read};
 use std::time::Duration;
 use std::str::FromStr;
 
+use crate::pidfile::PidFile;
+
 #[repr(i8)]
 pub enum  Request {
     All                      = -1,
@@ -57,54 +58,107 @@ pub enum RequestType {
     ReqLog                   = 15,
 }
 
+pub const TINC_CTL_VERSION_CURRENT: i32 = 0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct DaemonIdentity {
+    pub name:               String,
+    pub protocol_major:     i32,
+    pub protocol_minor:     i32,
+    pub ctl_version:        i32,
+    pub pid:                i32,
+}
+impl DaemonIdentity {
+    // tincd checks the cookie first. It answers a good one with its greeting
+    // `0 <name> <major>.<minor>` and the ack `4 <control version> <pid>`,
+    // and closes the connection on a wrong one without saying anything.
+    fn from(handshake: &str) -> Result<Self> {
+        if handshake.trim().is_empty() {
+            return Err(Error::new(ErrorKind::PermissionDenied,
+                                  "tincd rejected the control cookie, the pidfile may be stale."));
+        }
+        let mut greeting = None;
+        let mut ack = None;
+        for line in handshake.lines() {
+            let iter: Vec<&str> = line.split_whitespace().collect();
+            if iter.len() < 3 {
+                continue;
+            }
+            match iter[0].parse::<i8>() {
+                Ok(x) if x == Request::Id as i8 => greeting = Some(iter),
+                Ok(x) if x == Request::Ack as i8 => ack = Some(iter),
+                _ => (),
+            }
+        }
+
+        let greeting = greeting.ok_or_else(||
+            Error::new(ErrorKind::InvalidData, format!("Invalid greeting from tincd: {}", handshake.trim())))?;
+        let ack = ack.ok_or_else(||
+            Error::new(ErrorKind::PermissionDenied, "tincd did not acknowledge the control cookie."))?;
+
+        let mut protocol = greeting[2].split('.');
+        let protocol_major = protocol.next().and_then(|x| x.parse().ok()).unwrap_or(0);
+        let protocol_minor = protocol.next().and_then(|x| x.parse().ok()).unwrap_or(0);
+        let ctl_version = ack[1].parse()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "Invalid control protocol version."))?;
+        if ctl_version != TINC_CTL_VERSION_CURRENT {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported control protocol version {}, expected {}.",
+                        ctl_version, TINC_CTL_VERSION_CURRENT)));
+        }
+
+        Ok(DaemonIdentity {
+            name: greeting[1].to_string(),
+            protocol_major,
+            protocol_minor,
+            ctl_version,
+            pid: ack[2].parse().unwrap_or(0),
+        })
+    }
+}
+
 pub struct TincStream {
     stream: TcpStream,
+    identity: DaemonIdentity,
 }
 impl TincStream {
     pub fn new(pid_path: &str) -> Result<Self> {
-        let (control_cookie, tinc_ip, tinc_port) = Self::parse_control_cookie(pid_path)?;
-        let buf = format!("{} ^{} {}\n", 0, control_cookie, 17);
-        let addr = SocketAddr::from_str(&(tinc_ip + ":" + &tinc_port))
+        let pidfile = PidFile::read(pid_path)?;
+        let buf = format!("{} ^{} {}\n", 0, pidfile.cookie, 17);
+        let addr = SocketAddr::from_str(&(pidfile.address.clone() + ":" + &pidfile.port))
             .map_err(|_|ErrorKind::InvalidData)?;
 
-        let stream = TcpStream::connect(&addr)?;
+        let stream = TcpStream::connect(addr)
+            .map_err(|e| pidfile.diagnose(pid_path, Error::new(
+                e.kind(),
+                format!("tincd pid {} is running but not listening on {} port {}: {}",
+                        pidfile.pid, pidfile.address, pidfile.port, e))))?;
         let _ = stream.set_read_timeout(Some(Duration::from_millis(400)));
 
-        let mut tinc_stream = TincStream{stream};
-        tinc_stream.send_line(buf.as_bytes())?;
-        let _res = tinc_stream.recv()?;
-        return Ok(tinc_stream);
+        // Whatever answers may not be the tincd that wrote the pidfile.
+        let mut stream = stream;
+        let identity = stream.write_all(buf.as_bytes())
+            .and_then(|_| Self::recv_from(&mut stream))
+            .and_then(|res| DaemonIdentity::from(&res))
+            .map_err(|e| pidfile.diagnose(pid_path, e))?;
+        debug!("Attached to tincd {:?}", identity);
+        Ok(TincStream{stream, identity})
     }
 
-    fn send_line(&mut self, buf: &[u8]) -> Result<()> {
-        self.stream.write(buf)?;
-        return Ok(());
+    pub fn identity(&self) -> &DaemonIdentity {
+        &self.identity
     }
 
-    fn parse_control_cookie(path: &str) -> Result<(String, String, String)> {
-        let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let iter: Vec<&str> = contents.split_whitespace().collect();
-        let control_cookie = iter[1];
-
-        let mut _tinc_ip: &str = "";
-        let mut _tinc_port: &str = "";
-        if iter.len() < 3 {
-            error!("Tinc pid file, not find port setting. Maybe tinc tcp port never be set");
-            return Err(Error::new(ErrorKind::InvalidData, "Tinc pid file, not find port setting. Maybe tinc tcp port never be set"));
-        }
-        else if iter.len() >= 5 {
-            _tinc_ip = iter[2];
-            _tinc_port = iter[4];
-        }
-        return Ok((control_cookie.to_string(), _tinc_ip.to_string(), _tinc_port.to_string()));
+    fn send_line(&mut self, buf: &[u8]) -> Result<()> {
+        self.stream.write_all(buf)?;
+        Ok(())
     }
 
     pub fn stop(&mut self) -> Result<()> {
         let cmd = format!("{} {}\n", Request::Control as i8, RequestType::ReqStop as i8);
         self.send_line(cmd.as_bytes())?;
-        return Ok(());
+        Ok(())
     }
 
     pub fn reload(&mut self) -> Result<()> {
@@ -114,7 +168,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqReload as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Reload failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Reload failed."))
     }
 
     pub fn restart(&mut self) -> Result<()> {
@@ -124,7 +178,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqReload as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Restart failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Restart failed."))
     }
 
     pub fn dump_nodes(&mut self) -> Result<Vec<SourceNode>> {
@@ -142,7 +196,7 @@ impl TincStream {
         else {
             error!("dump_nodes check_res");
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Dump nodes failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Dump nodes failed."))
     }
 
     pub fn dump_edges(&mut self) -> Result<Vec<SourceEdge>> {
@@ -161,7 +215,7 @@ impl TincStream {
         else {
             error!("dump_edges check_res");
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Dump edges failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Dump edges failed."))
     }
 
     pub fn dump_subnets(&mut self) -> Result<Vec<SourceSubnet>> {
@@ -173,7 +227,7 @@ impl TincStream {
                 return Ok(source_subnet);
             }
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Dump subnets failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Dump subnets failed."))
     }
 
     pub fn dump_connections(&mut self) -> Result<Vec<SourceConnection>> {
@@ -185,7 +239,7 @@ impl TincStream {
                 return Ok(source_connection);
             }
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Dump connections failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Dump connections failed."))
     }
 
     pub fn dump_graph(&mut self) -> Result<()> {
@@ -195,7 +249,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqDumpGraph as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Dump graph failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Dump graph failed."))
     }
 
     pub fn purge(&mut self) -> Result<()> {
@@ -205,7 +259,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqPurge as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Purge failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Purge failed."))
     }
 
     pub fn set_debug(&mut self, debug_level: i8) -> Result<()> {
@@ -215,7 +269,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqSetDebug as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Set debug failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Set debug failed."))
     }
 
     pub fn retry(&mut self) -> Result<()> {
@@ -225,7 +279,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqRetry as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Retry failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Retry failed."))
     }
 
     pub fn connect(&mut self) -> Result<()> {
@@ -235,7 +289,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqConnect as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Connect failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Connect failed."))
     }
 
     pub fn disconnect(&mut self) -> Result<()> {
@@ -245,17 +299,19 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqDisconnect as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Disconnect failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Disconnect failed."))
     }
 
-    pub fn dump_traffic(&mut self) -> Result<()> {
+    pub fn dump_traffic(&mut self) -> Result<Vec<SourceTraffic>> {
         let cmd = format!("{} {}\n", Request::Control as i8, RequestType::ReqDumpTraffic as i8);
         self.send_line(cmd.as_bytes())?;
         let res = self.recv()?;
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqDumpTraffic as i8) {
-            return Ok(());
+            if let Ok(source_traffic) = SourceTraffic::from_traffic(&res) {
+                return Ok(source_traffic);
+            }
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Dump traffic failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Dump traffic failed."))
     }
 
     pub fn pcap(&mut self) -> Result<()> {
@@ -265,7 +321,7 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqPcap as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Pcap failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Pcap failed."))
     }
 
     pub fn log(&mut self) -> Result<()> {
@@ -275,23 +331,24 @@ impl TincStream {
         if Self::check_res(&res, Request::Control as i8, RequestType::ReqLog as i8) {
             return Ok(());
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Log failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Log failed."))
     }
 
     fn recv(&mut self) -> Result<String> {
+        Self::recv_from(&mut self.stream)
+    }
+
+    fn recv_from(stream: &mut TcpStream) -> Result<String> {
         let mut output = String::new();
         loop {
             let res = &mut [0; 1024];
-            let _len = match self.stream.read(res) {
-                Ok(x) => x,
-                Err(_) => 0,
-            };
+            let _len = stream.read(res).unwrap_or(0);
             if _len == 0 {
                 break
             }
             else {
                 let this = String::from_utf8_lossy(res).to_string().replace("\u{0}", "");
-                if this.len() == 0 {
+                if this.is_empty() {
                     break
                 }
                 else {
@@ -304,15 +361,12 @@ impl TincStream {
 
     fn check_res(res: &str, req: i8, req_type: i8) -> bool {
         let iter: Vec<&str> = res.split_whitespace().collect();
-        let control: i8 = match iter[0].parse() {
-            Ok(x) => x,
-            _ => -1,
-        };
-
-        let control_type: i8 = match iter[1].parse() {
-            Ok(x) => x,
-            _ => -1,
-        };
+        if iter.len() < 2 {
+            return false;
+        }
+        let control: i8 = iter[0].parse().unwrap_or(-1);
+
+        let control_type: i8 = iter[1].parse().unwrap_or(-1);
         if control == req && control_type == req_type {
             return true;
         }
@@ -386,7 +440,7 @@ impl SourceNode {
             };
             return Ok(source_node);
         }
-        return Err(Error::new(ErrorKind::InvalidData, "Parse SourceNode failed."));
+        Err(Error::new(ErrorKind::InvalidData, "Parse SourceNode failed."))
     }
 
     pub fn from_nodes(source_info: &str) -> Result<Vec<Self>> {
@@ -394,13 +448,13 @@ impl SourceNode {
         let nodes_str:Vec<&str> = source_info.split("\n").collect();
         let mut nodes: Vec<SourceNode> = vec![];
         for node_str in nodes_str.clone() {
-            if node_str.len() > 0 {
+            if !node_str.is_empty() {
                 if let Ok(node) = SourceNode::from(node_str) {
                     nodes.push(node)
                 }
             }
         }
-        return Ok(nodes);
+        Ok(nodes)
     }
 }
 
@@ -416,7 +470,8 @@ pub struct SourceEdge {
     pub local_port:     String,
     pub options:        String,
     pub weight:         String,
-//    avg_rtt:        String,
+    /// Only dumped by tincd 1.1.
+    pub avg_rtt:        Option<String>,
 }
 impl SourceEdge{
     fn from(source_str: &str) -> Result<Self> {
@@ -432,6 +487,7 @@ impl SourceEdge{
             let local_port = edge_str[9].to_string();
             let options = edge_str[10].to_string();
             let weight = edge_str[11].to_string();
+            let avg_rtt = edge_str.get(12).map(|x| x.to_string());
             return Ok(SourceEdge {
                 from,
                 to,
@@ -443,9 +499,10 @@ impl SourceEdge{
                 local_port,
                 options,
                 weight,
+                avg_rtt,
             });
         }
-        return Err(Error::new(ErrorKind::InvalidData, ""));
+        Err(Error::new(ErrorKind::InvalidData, ""))
     }
 
     fn from_edges(source_info: &str) -> Result<Vec<Self>> {
@@ -453,13 +510,13 @@ impl SourceEdge{
         let edges_str:Vec<&str> = source_info.split("\n").collect();
         let mut edges: Vec<SourceEdge> = vec![];
         for edge_str in edges_str.clone() {
-            if edge_str.len() > 0 {
+            if !edge_str.is_empty() {
                 if let Ok(edge) = SourceEdge::from(edge_str) {
                     edges.push(edge)
                 }
             }
         }
-        return Ok(edges);
+        Ok(edges)
     }
 }
 
@@ -479,7 +536,7 @@ impl SourceSubnet{
                 addr,
             });
         }
-        return Err(Error::new(ErrorKind::InvalidData, ""));
+        Err(Error::new(ErrorKind::InvalidData, ""))
     }
 
     fn from_subnets(source_info: &str) -> Result<Vec<Self>> {
@@ -487,13 +544,13 @@ impl SourceSubnet{
         let subnets_str:Vec<&str> = source_info.split("\n").collect();
         let mut subnets: Vec<Self> = vec![];
         for subnet_str in subnets_str.clone() {
-            if subnet_str.len() > 0 {
+            if !subnet_str.is_empty() {
                 if let Ok(subnet) = Self::from(subnet_str) {
                     subnets.push(subnet)
                 }
             }
         }
-        return Ok(subnets);
+        Ok(subnets)
     }
 }
 
@@ -528,7 +585,7 @@ impl SourceConnection{
                 status_int,
             });
         }
-        return Err(Error::new(ErrorKind::InvalidData, ""));
+        Err(Error::new(ErrorKind::InvalidData, ""))
     }
 
     fn from_connections(source_info: &str) -> Result<Vec<Self>> {
@@ -536,12 +593,52 @@ impl SourceConnection{
         let connections_str:Vec<&str> = source_info.split("\n").collect();
         let mut connections: Vec<Self> = vec![];
         for connection_str in connections_str.clone() {
-            if connection_str.len() > 0 {
+            if !connection_str.is_empty() {
                 if let Ok(connection) = Self::from(connection_str) {
                     connections.push(connection)
                 }
             }
         }
-        return Ok(connections);
+        Ok(connections)
+    }
+}
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SourceTraffic {
+    pub node:           String,
+    pub in_packets:     String,
+    pub in_bytes:       String,
+    pub out_packets:    String,
+    pub out_bytes:      String,
+}
+impl SourceTraffic {
+    fn from(source_str: &str) -> Result<Self> {
+        let traffic_str:Vec<&str> = source_str.split(" ").collect();
+        if traffic_str.len() >= 7 {
+            let node = traffic_str[2].to_string();
+            let in_packets = traffic_str[3].to_string();
+            let in_bytes = traffic_str[4].to_string();
+            let out_packets = traffic_str[5].to_string();
+            let out_bytes = traffic_str[6].to_string();
+            return Ok(SourceTraffic {
+                node,
+                in_packets,
+                in_bytes,
+                out_packets,
+                out_bytes,
+            });
+        }
+        Err(Error::new(ErrorKind::InvalidData, "Parse SourceTraffic failed."))
+    }
+
+    fn from_traffic(source_info: &str) -> Result<Vec<Self>> {
+        let mut traffic: Vec<Self> = vec![];
+        for traffic_str in source_info.split("\n") {
+            if !traffic_str.is_empty() {
+                if let Ok(node_traffic) = Self::from(traffic_str) {
+                    traffic.push(node_traffic)
+                }
+            }
+        }
+        Ok(traffic)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,336 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Result, Write};
+use std::path::{Path, PathBuf};
+
+use crate::domain::Data;
+
+pub const DEFAULT_TSDB_DIR: &str = "/var/lib/tincmgr/tsdb";
+
+const COMPACT_INTERVAL: i64 = 3600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resolution {
+    #[serde(rename = "raw")]
+    Raw,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "1h")]
+    Hourly,
+}
+impl Resolution {
+    pub const ALL: [Resolution; 3] = [Resolution::Raw, Resolution::FiveMinutes, Resolution::Hourly];
+
+    pub fn parse(resolution: &str) -> Option<Self> {
+        match resolution {
+            "raw" => Some(Resolution::Raw),
+            "5m" => Some(Resolution::FiveMinutes),
+            "1h" => Some(Resolution::Hourly),
+            _ => None,
+        }
+    }
+
+    /// Bucket length in seconds. Raw points keep the poll times.
+    pub fn step(self) -> i64 {
+        match self {
+            Resolution::Raw => 0,
+            Resolution::FiveMinutes => 300,
+            Resolution::Hourly => 3600,
+        }
+    }
+
+    /// How long points of this resolution are kept, in seconds.
+    pub fn retention(self) -> i64 {
+        match self {
+            Resolution::Raw => 86400,
+            Resolution::FiveMinutes => 30 * 86400,
+            Resolution::Hourly => 365 * 86400,
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Resolution::Raw => "raw.tsv",
+            Resolution::FiveMinutes => "5m.tsv",
+            Resolution::Hourly => "1h.tsv",
+        }
+    }
+}
+
+/// A metric of one node or link, or of the whole network when `subject`
+/// is empty.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct SeriesId {
+    pub metric:     String,
+    pub subject:    String,
+}
+impl SeriesId {
+    pub fn new(metric: &str, subject: &str) -> Self {
+        SeriesId {
+            metric: metric.to_string(),
+            subject: subject.to_string(),
+        }
+    }
+}
+
+/// A raw sample, or the mean, min and max of a downsampled bucket.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub time:       i64,
+    pub value:      f64,
+    pub min:        f64,
+    pub max:        f64,
+}
+impl Point {
+    fn aggregate(time: i64, points: &[&Point]) -> Self {
+        Point {
+            time,
+            value: points.iter().map(|x| x.value).sum::<f64>() / points.len() as f64,
+            min: points.iter().map(|x| x.min).fold(f64::INFINITY, f64::min),
+            max: points.iter().map(|x| x.max).fold(f64::NEG_INFINITY, f64::max),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Series {
+    #[serde(flatten)]
+    pub id:         SeriesId,
+    pub resolution: Resolution,
+    pub points:     Vec<Point>,
+}
+
+type Level = BTreeMap<SeriesId, VecDeque<Point>>;
+
+/// Lines one `record` leaves to write, per resolution: appended, or
+/// replacing the whole file when compacting.
+#[must_use]
+pub struct TsdbWrite {
+    dir:            Option<PathBuf>,
+    compact:        bool,
+    lines:          [String; 3],
+}
+impl TsdbWrite {
+    pub fn save(self) -> Result<()> {
+        let dir = match self.dir {
+            Some(dir) => dir,
+            None => return Ok(()),
+        };
+        for (lines, resolution) in self.lines.iter().zip(Resolution::ALL.iter()) {
+            let path = dir.join(resolution.file_name());
+            if self.compact {
+                let tmp = path.with_extension("tmp");
+                let mut file = File::create(&tmp)?;
+                file.write_all(lines.as_bytes())?;
+                file.sync_all()?;
+                fs::rename(&tmp, &path)?;
+            } else if !lines.is_empty() {
+                let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+                file.write_all(lines.as_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Series of what every poll saw, kept in memory and appended to one file
+/// per resolution under `dir`. Finished buckets are downsampled into the
+/// next resolution as polls move past them.
+#[derive(Default)]
+pub struct Tsdb {
+    dir:            Option<PathBuf>,
+    levels:         [Level; 3],
+    last_time:      i64,
+    last_compact:   i64,
+}
+impl Tsdb {
+    /// Open the store under `dir`, creating it if needed, and load what
+    /// earlier runs recorded.
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut tsdb = Tsdb {
+            dir: Some(dir.to_path_buf()),
+            ..Tsdb::default()
+        };
+        for (i, resolution) in Resolution::ALL.iter().enumerate() {
+            let path = dir.join(resolution.file_name());
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(_) => continue,
+            };
+            for line in BufReader::new(file).lines() {
+                let line = line?;
+                match parse_line(&line) {
+                    Some((id, point)) => {
+                        tsdb.last_time = tsdb.last_time.max(point.time);
+                        push(&mut tsdb.levels[i], id, point);
+                    }
+                    None => warn!("Invalid line in {}: {}", path.display(), line),
+                }
+            }
+        }
+        Ok(tsdb)
+    }
+
+    /// Add the samples of a poll in memory. What has to go to disk is
+    /// returned, to be saved without holding up readers of the store.
+    pub fn record(&mut self, time: i64, samples: &[(SeriesId, f64)]) -> TsdbWrite {
+        let mut added: [Vec<(SeriesId, Point)>; 3] = Default::default();
+        for (id, value) in samples {
+            let point = Point { time, value: *value, min: *value, max: *value };
+            if push(&mut self.levels[0], id.clone(), point.clone()) {
+                added[0].push((id.clone(), point));
+            }
+        }
+        for i in 1..self.levels.len() {
+            let step = Resolution::ALL[i].step();
+            let current = time - time.rem_euclid(step);
+            let (finer, coarser) = self.levels.split_at_mut(i);
+            for (id, points) in finer[i - 1].iter() {
+                let start = match coarser[0].get(id).and_then(|x| x.back()) {
+                    Some(last) => last.time + step,
+                    None => i64::MIN,
+                };
+                let mut pending: Vec<&Point> = points.iter().rev()
+                    .take_while(|x| x.time >= start)
+                    .filter(|x| x.time < current)
+                    .collect();
+                pending.reverse();
+                let mut buckets: Vec<(i64, Vec<&Point>)> = vec![];
+                for point in pending {
+                    let bucket = point.time - point.time.rem_euclid(step);
+                    match buckets.last_mut() {
+                        Some((time, points)) if *time == bucket => points.push(point),
+                        _ => buckets.push((bucket, vec![point])),
+                    }
+                }
+                for (bucket, points) in buckets {
+                    added[i].push((id.clone(), Point::aggregate(bucket, &points)));
+                }
+            }
+            for (id, point) in added[i].iter() {
+                push(&mut self.levels[i], id.clone(), point.clone());
+            }
+        }
+        self.last_time = self.last_time.max(time);
+
+        for (i, resolution) in Resolution::ALL.iter().enumerate() {
+            let oldest = time - resolution.retention();
+            for points in self.levels[i].values_mut() {
+                while points.front().is_some_and(|x| x.time <= oldest) {
+                    points.pop_front();
+                }
+            }
+            self.levels[i].retain(|_, points| !points.is_empty());
+        }
+
+        let mut write = TsdbWrite {
+            dir: self.dir.clone(),
+            compact: false,
+            lines: Default::default(),
+        };
+        if self.dir.is_some() && time - self.last_compact >= COMPACT_INTERVAL {
+            // Rewrite the files without the expired points.
+            write.compact = true;
+            for (i, level) in self.levels.iter().enumerate() {
+                write.lines[i] = level.iter()
+                    .flat_map(|(id, points)| points.iter().map(move |point| format_line(id, point)))
+                    .collect();
+            }
+            self.last_compact = time;
+        } else {
+            for (i, added) in added.iter().enumerate() {
+                write.lines[i] = added.iter().map(|(id, point)| format_line(id, point)).collect();
+            }
+        }
+        write
+    }
+
+    pub fn series(&self) -> Vec<SeriesId> {
+        let ids: BTreeSet<&SeriesId> = self.levels.iter().flat_map(|x| x.keys()).collect();
+        ids.into_iter().cloned().collect()
+    }
+
+    /// Points of `id` between `from` and `to`. Without a resolution, the
+    /// finest one still covering `from` is used.
+    pub fn query(&self, id: &SeriesId, from: i64, to: i64, resolution: Option<Resolution>) -> Option<Series> {
+        let resolution = resolution.unwrap_or_else(|| {
+            *Resolution::ALL.iter()
+                .find(|x| from > self.last_time - x.retention())
+                .unwrap_or(&Resolution::Hourly)
+        });
+        let i = Resolution::ALL.iter().position(|x| *x == resolution)?;
+        if !self.levels.iter().any(|x| x.contains_key(id)) {
+            return None;
+        }
+        let points = match self.levels[i].get(id) {
+            Some(points) => points.iter().filter(|x| x.time >= from && x.time <= to).cloned().collect(),
+            None => vec![],
+        };
+        Some(Series {
+            id: id.clone(),
+            resolution,
+            points,
+        })
+    }
+}
+
+/// What one poll records: reachability, PMTU and traffic counters of
+/// every other node, reachability, weight and RTT of every link, and
+/// network totals.
+pub fn samples(data: &Data) -> Vec<(SeriesId, f64)> {
+    let mut samples = vec![];
+    for node in data.nodes.iter().filter(|x| !x.local) {
+        samples.push((SeriesId::new("node_reachable", &node.name), node.reachable as f64));
+        if node.reachable == 1 && node.pmtu > 0 {
+            samples.push((SeriesId::new("node_pmtu", &node.name), node.pmtu as f64));
+        }
+        if let Some(ref traffic) = node.traffic {
+            samples.push((SeriesId::new("node_in_packets", &node.name), traffic.in_packets as f64));
+            samples.push((SeriesId::new("node_in_bytes", &node.name), traffic.in_bytes as f64));
+            samples.push((SeriesId::new("node_out_packets", &node.name), traffic.out_packets as f64));
+            samples.push((SeriesId::new("node_out_bytes", &node.name), traffic.out_bytes as f64));
+        }
+    }
+    for link in data.links.iter() {
+        samples.push((SeriesId::new("link_reachable", &link._hash), link.reachable as f64));
+        samples.push((SeriesId::new("link_weight", &link._hash), link.weight as f64));
+        if let Some(avg_rtt) = link.avg_rtt {
+            samples.push((SeriesId::new("link_rtt", &link._hash), avg_rtt as f64));
+        }
+    }
+    let reachable = data.nodes.iter().filter(|x| x.reachable == 1).count();
+    samples.push((SeriesId::new("nodes_reachable", ""), reachable as f64));
+    samples.push((SeriesId::new("links", ""), data.links.len() as f64));
+    let meta = data.connections.iter().filter(|x| x.is_meta()).count();
+    samples.push((SeriesId::new("meta_connections", ""), meta as f64));
+    samples
+}
+
+/// Append a point unless the series already has one at or after its time.
+fn push(level: &mut Level, id: SeriesId, point: Point) -> bool {
+    let points = level.entry(id).or_default();
+    if points.back().is_some_and(|x| x.time >= point.time) {
+        return false;
+    }
+    points.push_back(point);
+    true
+}
+
+fn format_line(id: &SeriesId, point: &Point) -> String {
+    format!("{}\t{}\t{}\t{}\t{}\t{}\n", point.time, id.metric, id.subject, point.value, point.min, point.max)
+}
+
+fn parse_line(line: &str) -> Option<(SeriesId, Point)> {
+    let fields: Vec<&str> = line.split('\t').collect();
+    if fields.len() != 6 {
+        return None;
+    }
+    let point = Point {
+        time: fields[0].parse().ok()?,
+        value: fields[3].parse().ok()?,
+        min: fields[4].parse().ok()?,
+        max: fields[5].parse().ok()?,
+    };
+    Some((SeriesId::new(fields[1], fields[2]), point))
+}
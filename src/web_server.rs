@@ -1,14 +1,23 @@
 extern crate actix_web;
 
 use actix_web::fs::StaticFiles;
-use actix_web::{middleware, App, server::HttpServer};
+use actix_web::{middleware, App, HttpRequest, HttpResponse, server::HttpServer};
 
-pub fn web_server(port: &str, data_dir: &str) -> std::io::Result<()> {
+use crate::accounting::Period;
+use crate::availability;
+use crate::domain::info::NodeInfo;
+use crate::domain::lookup;
+use crate::domain::subnets::Net;
+use crate::domain::trace;
+use crate::metrics;
+use crate::state::SharedState;
+use crate::tsdb::{Resolution, SeriesId};
+
+pub fn web_server(port: &str, data_dir: &str, state: SharedState) -> std::io::Result<()> {
     let data_dir = data_dir.to_string();
 
     HttpServer::new(move|| {
-        App::new()
-            .middleware(middleware::Logger::default())
+        app(state.clone())
             .handler("/",
                      StaticFiles::new(
                          &data_dir)
@@ -19,4 +28,265 @@ pub fn web_server(port: &str, data_dir: &str) -> std::io::Result<()> {
         .bind("0.0.0.0:".to_string() + port)?
         .run();
     Ok(())
-}
\ No newline at end of file
+}
+
+pub fn app(state: SharedState) -> App<SharedState> {
+    App::with_state(state)
+        .middleware(middleware::Logger::default())
+        .resource("/api/allowlist", |r| r.get().f(allowlist))
+        .resource("/api/audit", |r| r.get().f(audit))
+        .resource("/api/availability", |r| r.get().f(availability))
+        .resource("/api/conflicts", |r| r.get().f(conflicts))
+        .resource("/api/daemon", |r| r.get().f(daemon))
+        .resource("/api/daemon/process", |r| r.get().f(process))
+        .resource("/api/endpoints", |r| r.get().f(endpoints))
+        .resource("/api/events", |r| r.get().f(events))
+        .resource("/api/flaps", |r| r.get().f(flaps))
+        .resource("/api/interface", |r| r.get().f(interface))
+        .resource("/api/latency", |r| r.get().f(latency))
+        .resource("/api/lookup/{addr}", |r| r.get().f(lookup))
+        .resource("/api/nodes/{name}", |r| r.get().f(node_info))
+        .resource("/api/pmtu", |r| r.get().f(pmtu))
+        .resource("/api/purge", |r| r.get().f(purge))
+        .resource("/api/quotas", |r| r.get().f(quotas))
+        .resource("/api/routes", |r| r.get().f(routes))
+        .resource("/api/series", |r| r.get().f(series_list))
+        .resource("/api/series/{metric}", |r| r.get().f(series))
+        .resource("/api/trace/{destination}", |r| r.get().f(trace))
+        .resource("/api/traffic", |r| r.get().f(traffic))
+        .resource("/api/traffic/{name}", |r| r.get().f(node_traffic))
+        .resource("/metrics", |r| r.get().f(prometheus))
+}
+
+fn prometheus(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok()
+        .content_type("text/plain; version=0.0.4")
+        .body(metrics::render(&state))
+}
+
+fn process(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    match state.process {
+        Some(ref process) => HttpResponse::Ok().json(process),
+        None => HttpResponse::ServiceUnavailable().finish(),
+    }
+}
+
+fn daemon(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    if let Some(ref error) = state.attach_error {
+        return HttpResponse::ServiceUnavailable().json(json!({ "error": error }));
+    }
+    match state.data.as_ref().and_then(|data| data.daemon.as_ref()) {
+        Some(daemon) => HttpResponse::Ok().json(daemon),
+        None => HttpResponse::ServiceUnavailable().finish(),
+    }
+}
+
+fn endpoints(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.endpoints)
+}
+
+fn events(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.events)
+}
+
+fn interface(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.interface)
+}
+
+fn lookup(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let addr = req.match_info().get("addr").unwrap_or("");
+    let address = match Net::parse_address(addr) {
+        Some(address) => address,
+        None => return HttpResponse::BadRequest().json(json!({ "error": format!("Invalid address {}", addr) })),
+    };
+    let state = req.state().read().unwrap();
+    let data = match state.data {
+        Some(ref data) => data,
+        None => return HttpResponse::ServiceUnavailable().finish(),
+    };
+    match lookup::lookup(data, &address) {
+        Some(lookup) => HttpResponse::Ok().json(lookup),
+        None => HttpResponse::NotFound().json(json!({ "error": format!("No subnet contains {}", addr) })),
+    }
+}
+
+fn allowlist(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.allowlist)
+}
+
+fn audit(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.audit)
+}
+
+fn conflicts(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.conflicts)
+}
+
+fn node_info(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let name = req.match_info().get("name").unwrap_or("");
+    let state = req.state().read().unwrap();
+    let data = match state.data {
+        Some(ref data) => data,
+        None => return HttpResponse::ServiceUnavailable().finish(),
+    };
+    match NodeInfo::from(data, name) {
+        Some(info) => HttpResponse::Ok().json(info),
+        None => HttpResponse::NotFound().json(json!({ "error": format!("Unknown node {}", name) })),
+    }
+}
+
+fn trace(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let destination = req.match_info().get("destination").unwrap_or("");
+    let state = req.state().read().unwrap();
+    let data = match state.data {
+        Some(ref data) => data,
+        None => return HttpResponse::ServiceUnavailable().finish(),
+    };
+    match trace::trace(data, destination) {
+        Some(trace) => HttpResponse::Ok().json(trace),
+        None => HttpResponse::NotFound().json(json!({ "error": format!("Unknown destination {}", destination) })),
+    }
+}
+
+/// `?month=YYYY-MM`, or `?from=<unix time>&to=<unix time>` defaulting to the
+/// last 30 days, and `&format=json|csv|html`.
+fn availability(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let query = req.query();
+    let (from, to) = match query.get("month") {
+        Some(month) => match availability::month(month) {
+            Some(period) => period,
+            None => return HttpResponse::BadRequest().json(json!({ "error": format!("Invalid month {}", month) })),
+        },
+        None => {
+            let to = match query.get("to").map(|x| x.parse::<i64>()) {
+                Some(Ok(to)) => to,
+                Some(Err(_)) => return HttpResponse::BadRequest().json(json!({ "error": "Invalid to" })),
+                None => chrono::Local::now().timestamp(),
+            };
+            match query.get("from").map(|x| x.parse::<i64>()) {
+                Some(Ok(from)) => (from, to),
+                Some(Err(_)) => return HttpResponse::BadRequest().json(json!({ "error": "Invalid from" })),
+                None => (to - 30 * 86400, to),
+            }
+        }
+    };
+    let report = availability::report(&req.state().read().unwrap().series, from, to);
+    match query.get("format").map(String::as_str) {
+        None | Some("json") => HttpResponse::Ok().json(report),
+        Some("csv") => HttpResponse::Ok()
+            .content_type("text/csv")
+            .header("Content-Disposition", "attachment; filename=\"availability.csv\"")
+            .body(report.to_csv()),
+        Some("html") => HttpResponse::Ok()
+            .content_type("text/html; charset=utf-8")
+            .body(report.to_html()),
+        Some(format) => HttpResponse::BadRequest().json(json!({ "error": format!("Invalid format {}", format) })),
+    }
+}
+
+fn series_list(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(state.series.series())
+}
+
+/// `?subject=<node or link>&from=<unix time>&to=<unix time>&resolution=raw|5m|1h`,
+/// by default the last day of the network wide series.
+fn series(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let metric = req.match_info().get("metric").unwrap_or("");
+    let query = req.query();
+    let subject = query.get("subject").map(String::as_str).unwrap_or("");
+    let to = match query.get("to").map(|x| x.parse::<i64>()) {
+        Some(Ok(to)) => to,
+        Some(Err(_)) => return HttpResponse::BadRequest().json(json!({ "error": "Invalid to" })),
+        None => chrono::Local::now().timestamp(),
+    };
+    let from = match query.get("from").map(|x| x.parse::<i64>()) {
+        Some(Ok(from)) => from,
+        Some(Err(_)) => return HttpResponse::BadRequest().json(json!({ "error": "Invalid from" })),
+        None => to - 86400,
+    };
+    let resolution = match query.get("resolution").map(|x| Resolution::parse(x)) {
+        Some(Some(resolution)) => Some(resolution),
+        Some(None) => return HttpResponse::BadRequest().json(json!({ "error": "Invalid resolution" })),
+        None => None,
+    };
+    let state = req.state().read().unwrap();
+    match state.series.query(&SeriesId::new(metric, subject), from, to, resolution) {
+        Some(series) => HttpResponse::Ok().json(series),
+        None => HttpResponse::NotFound().json(json!({ "error": format!("Unknown series {} {}", metric, subject) })),
+    }
+}
+
+/// `?period=day|month|total&key=YYYY-MM-DD|YYYY-MM&limit=N`, by default
+/// today's usage of every node.
+fn traffic(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let query = req.query();
+    let period = match query.get("period").map(|x| Period::parse(x)) {
+        Some(Some(period)) => period,
+        Some(None) => return HttpResponse::BadRequest().json(json!({ "error": "Invalid period" })),
+        None => Period::Day,
+    };
+    let key = match query.get("key") {
+        Some(key) => key.clone(),
+        None => period.key(chrono::Local::now().timestamp()),
+    };
+    let limit = match query.get("limit").map(|x| x.parse::<usize>()) {
+        Some(Ok(limit)) => Some(limit),
+        Some(Err(_)) => return HttpResponse::BadRequest().json(json!({ "error": "Invalid limit" })),
+        None => None,
+    };
+    let state = req.state().read().unwrap();
+    let mut report = state.traffic.report(period, &key);
+    if let Some(limit) = limit {
+        report.nodes.truncate(limit);
+    }
+    HttpResponse::Ok().json(report)
+}
+
+fn node_traffic(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let name = req.match_info().get("name").unwrap_or("");
+    let state = req.state().read().unwrap();
+    match state.traffic.nodes.get(name) {
+        Some(account) => HttpResponse::Ok().json(account),
+        None => HttpResponse::NotFound().json(json!({ "error": format!("Unknown node {}", name) })),
+    }
+}
+
+fn routes(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.routes)
+}
+
+fn latency(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.latency)
+}
+
+fn flaps(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.flaps)
+}
+
+fn pmtu(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.pmtu)
+}
+
+fn quotas(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.quotas)
+}
+
+fn purge(req: &HttpRequest<SharedState>) -> HttpResponse {
+    let state = req.state().read().unwrap();
+    HttpResponse::Ok().json(&state.purge)
+}
@@ -0,0 +1,74 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::accounting::{Period, TrafficAccounting};
+use tincmgr::metrics;
+use tincmgr::mock_tincd::{MockNode, MockTincd, Topology};
+use tincmgr::state::State;
+
+use common::{temp_dir, TIME};
+
+fn counters(beta: (u64, u64, u64, u64), carol: (u64, u64, u64, u64)) -> Topology {
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(MockNode::new("beta"))
+        .node(MockNode::new("carol"))
+        .traffic("beta", beta.0, beta.1, beta.2, beta.3)
+        .traffic("carol", carol.0, carol.1, carol.2, carol.3)
+}
+
+#[test]
+fn accumulates_across_resets() {
+    let mock = MockTincd::start(counters((10, 1000, 10, 2000), (0, 0, 0, 0))).unwrap();
+    let mut accounting = TrafficAccounting::default();
+    accounting.update(&common::load(&mock), TIME).unwrap();
+    assert_eq!(accounting.nodes["beta"].total.bytes(), 0);
+
+    mock.set_topology(counters((20, 1500, 30, 4000), (0, 0, 0, 0)));
+    accounting.update(&common::load(&mock), TIME + 60).unwrap();
+    // tincd restarted and counts from zero again.
+    mock.set_topology(counters((1, 100, 1, 100), (0, 0, 0, 0)));
+    let mut data = common::load(&mock);
+    accounting.update(&data, TIME + 120).unwrap();
+
+    let beta = &accounting.nodes["beta"];
+    assert_eq!((beta.total.in_bytes, beta.total.out_bytes, beta.total.out_packets), (600, 2100, 21));
+    assert_eq!(beta.resets, 1);
+    let days: u64 = beta.days.values().map(|x| x.bytes()).sum();
+    assert_eq!(days, 2700);
+
+    // A restart that already counted more than before is told by the pid.
+    data.daemon.as_mut().unwrap().pid += 1;
+    accounting.update(&data, TIME + 180).unwrap();
+    let beta = &accounting.nodes["beta"];
+    assert_eq!((beta.total.in_bytes, beta.resets), (700, 2));
+
+    let text = metrics::render(&State { traffic: accounting, ..State::default() });
+    assert!(text.contains("tincmgr_node_traffic_bytes_total{node=\"beta\",direction=\"out\"} 2200\n"));
+}
+
+#[test]
+fn ranks_top_talkers_and_persists() {
+    let dir = temp_dir("traffic");
+    let path = dir.join("traffic.json");
+    let mock = MockTincd::start(counters((0, 0, 0, 0), (0, 0, 0, 0))).unwrap();
+    {
+        let mut accounting = TrafficAccounting::open(&path).unwrap();
+        // The first poll of a tincd is saved right away, changes after it
+        // only every few minutes.
+        accounting.update(&common::load(&mock), TIME).unwrap().unwrap().save().unwrap();
+        mock.set_topology(counters((1, 100, 1, 100), (5, 5000, 1, 10)));
+        assert!(accounting.update(&common::load(&mock), TIME + 60).unwrap().is_none());
+        accounting.write().unwrap().unwrap().save().unwrap();
+    }
+
+    let accounting = TrafficAccounting::open(&path).unwrap();
+    let month = Period::Month.key(TIME + 60);
+    let report = accounting.report(Period::Month, &month);
+    let names: Vec<&str> = report.nodes.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, vec!["carol", "beta"]);
+    assert_eq!(report.total.bytes(), 5210);
+    assert!(accounting.report(Period::Month, "1999-01").nodes.is_empty());
+    assert_eq!(accounting.report(Period::Total, "").nodes[0].traffic.in_bytes, 5000);
+}
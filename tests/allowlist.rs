@@ -0,0 +1,81 @@
+extern crate tincmgr;
+
+mod common;
+
+use std::fs;
+
+use tincmgr::allowlist::{Allowlist, AllowlistMonitor, VanishedNode};
+use tincmgr::events::EventKind;
+use tincmgr::metrics;
+use tincmgr::mock_tincd::{MockNode, MockTincd, Topology};
+use tincmgr::state::State;
+
+use common::{temp_dir, TIME};
+
+fn names(allowlist: &Allowlist) -> Vec<&str> {
+    allowlist.names.iter().map(String::as_str).collect()
+}
+
+#[test]
+fn reads_lists_and_hosts() {
+    let allowlist = Allowlist::parse("alpha beta # the hubs\n\n# branches\ncarol\n").unwrap();
+    assert_eq!(names(&allowlist), vec!["alpha", "beta", "carol"]);
+    assert!(Allowlist::parse("alpha bad-name\n").is_err());
+
+    let hosts = temp_dir("allowlist-hosts");
+    for name in ["alpha", "beta", "tinc-up", "beta-down"].iter() {
+        fs::write(hosts.join(name), "Subnet = 10.0.0.0/24\n").unwrap();
+    }
+    fs::create_dir(hosts.join("old")).unwrap();
+    // Host files kept elsewhere and linked in count, links to directories not.
+    std::os::unix::fs::symlink(hosts.join("alpha"), hosts.join("carol")).unwrap();
+    std::os::unix::fs::symlink(hosts.join("old"), hosts.join("dave")).unwrap();
+    assert_eq!(names(&Allowlist::load(&hosts).unwrap()), vec!["alpha", "beta", "carol"]);
+}
+
+#[test]
+fn flags_unknown_never_seen_unreachable_and_vanished() {
+    let dir = temp_dir("allowlist-monitor");
+    let list = dir.join("allowlist");
+    fs::write(&list, "alpha beta dave erin\n").unwrap();
+
+    let mock = MockTincd::start(Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(MockNode::new("beta"))
+        .node(MockNode::new("carol"))
+        .node(MockNode::new("dave"))).unwrap();
+    let mut monitor = AllowlistMonitor::new(Some(list.clone()));
+    let (report, events) = monitor.update(&common::load(&mock), TIME);
+    assert_eq!(report.expected, 4);
+    assert_eq!(report.unknown, vec!["carol"]);
+    assert_eq!(report.never_seen, vec!["erin"]);
+    assert!(report.unreachable.is_empty());
+    assert!(report.vanished.is_empty());
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].node, "carol");
+    assert_eq!(events[0].kind, EventKind::UnknownNode { reachable: true });
+
+    mock.set_topology(Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(MockNode::new("beta").unreachable())
+        .node(MockNode::new("carol").unreachable()));
+    let (report, events) = monitor.update(&common::load(&mock), TIME + 20);
+    assert!(events.is_empty());
+    assert_eq!(report.unknown, vec!["carol"]);
+    assert_eq!(report.never_seen, vec!["erin"]);
+    assert_eq!(report.unreachable, vec!["beta"]);
+    assert_eq!(report.vanished, vec![VanishedNode { name: "dave".to_string(), last_seen: TIME }]);
+
+    // Adding carol to the list is picked up on the next poll.
+    fs::write(&list, "alpha beta carol dave erin\n").unwrap();
+    let (report, _) = monitor.update(&common::load(&mock), TIME + 40);
+    assert!(report.unknown.is_empty());
+    assert_eq!(report.unreachable, vec!["beta", "carol"]);
+    assert_eq!(report.vanished.len(), 1);
+
+    let text = metrics::render(&State { allowlist: report, ..State::default() });
+    assert!(text.contains("tincmgr_allowlist_nodes{state=\"expected\"} 5\n"));
+    assert!(text.contains("tincmgr_allowlist_nodes{state=\"unreachable\"} 2\n"));
+    assert!(text.contains("tincmgr_allowlist_nodes{state=\"vanished\"} 1\n"));
+    assert!(!text.contains("tincmgr_node_unknown{"));
+}
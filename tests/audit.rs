@@ -0,0 +1,84 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::domain::audit::{self, FindingKind, Severity};
+use tincmgr::metrics;
+use tincmgr::mock_tincd::{MockNode, MockTincd, Topology};
+use tincmgr::state::State;
+
+fn legacy(name: &str, cipher: i32, digest: i32, maclength: i32) -> MockNode {
+    let mut node = MockNode::new(name);
+    node.options = 0x0000_000c;
+    node.cipher = cipher;
+    node.digest = digest;
+    node.maclength = maclength;
+    node
+}
+
+fn sptps(name: &str) -> MockNode {
+    let mut node = MockNode::new(name);
+    node.status |= 0x0000_0040;
+    node
+}
+
+// A legacy node no packet was sent to yet: no key, cipher and digest 0.
+fn idle(name: &str) -> MockNode {
+    let mut node = legacy(name, 0, 0, 0);
+    node.status &= !0x0000_0002;
+    node
+}
+
+fn mixed() -> Topology {
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(sptps("beta"))
+        .node(legacy("carol", 91, 64, 4))
+        .node(legacy("dave", 0, 0, 0))
+        .node(legacy("erin", 427, 672, 16))
+        .node(legacy("frank", 0, 0, 0).unreachable())
+        .node(MockNode::new("gina"))
+        .node(idle("hank"))
+}
+
+#[test]
+fn flags_weak_crypto() {
+    let mock = MockTincd::start(mixed()).unwrap();
+    let report = audit::audit(&common::load(&mock));
+
+    assert_eq!(report.checked, 6);
+    let findings: Vec<(&str, &FindingKind)> = report.findings.iter().map(|x| (x.node.as_str(), &x.kind)).collect();
+    assert_eq!(findings, vec![
+        ("carol", &FindingKind::LegacyProtocol),
+        ("carol", &FindingKind::WeakCipher { cipher: "bf-cbc".to_string() }),
+        ("carol", &FindingKind::WeakDigest { digest: "sha1".to_string() }),
+        ("carol", &FindingKind::ShortMac { length: 4 }),
+        ("dave", &FindingKind::LegacyProtocol),
+        ("dave", &FindingKind::NoEncryption),
+        ("dave", &FindingKind::NoDigest),
+        ("erin", &FindingKind::LegacyProtocol),
+        ("gina", &FindingKind::LegacyProtocol),
+        ("gina", &FindingKind::NoEncryption),
+        ("gina", &FindingKind::NoDigest),
+        ("hank", &FindingKind::LegacyProtocol),
+        ("hank", &FindingKind::KeyNotNegotiated),
+    ]);
+    assert_eq!(report.count(Severity::Critical), 4);
+    assert_eq!(audit::cipher_name(427), "aes-256-cbc");
+    assert_eq!(audit::digest_name(12345), "nid 12345");
+}
+
+#[test]
+fn summarizes_protocol_versions() {
+    let mock = MockTincd::start(mixed()).unwrap();
+    let report = audit::audit(&common::load(&mock));
+
+    let versions: Vec<(u8, bool, usize)> = report.versions.iter()
+        .map(|x| (x.protocol_minor, x.sptps, x.nodes.len()))
+        .collect();
+    assert_eq!(versions, vec![(0, false, 4), (7, false, 1), (7, true, 1)]);
+
+    let text = metrics::render(&State { data: Some(common::load(&mock)), audit: report, ..State::default() });
+    assert!(text.contains("tincmgr_audit_findings{severity=\"critical\"} 4\n"));
+    assert!(text.contains("tincmgr_nodes_protocol{minor=\"0\",sptps=\"0\"} 4\n"));
+}
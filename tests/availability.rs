@@ -0,0 +1,49 @@
+extern crate tincmgr;
+
+use tincmgr::availability;
+use tincmgr::tsdb::{SeriesId, Tsdb};
+
+fn history() -> Tsdb {
+    let mut tsdb = Tsdb::default();
+    let reachable = [1, 1, 1, 1, 1, 1, 0, 0, 1, 1, 1, 0, 1];
+    for (i, value) in reachable.iter().enumerate() {
+        tsdb.record(i as i64 * 20, &[
+            (SeriesId::new("node_reachable", "beta"), *value as f64),
+            (SeriesId::new("link_reachable", "alpha-beta"), 1.0),
+        ]).save().unwrap();
+    }
+    // tincmgr was not running in between.
+    tsdb.record(1000, &[(SeriesId::new("node_reachable", "beta"), 1.0)]).save().unwrap();
+    tsdb
+}
+
+#[test]
+fn computes_outages() {
+    let report = availability::report(&history(), 0, 1000);
+
+    let beta = &report.nodes[0];
+    assert_eq!(beta.subject, "beta");
+    assert_eq!((beta.observed, beta.downtime), (240, 60));
+    assert_eq!(beta.availability, Some(75.0));
+    assert_eq!((beta.outages, beta.longest_outage, beta.mttr), (2, 40, Some(30)));
+    let link = &report.links[0];
+    assert_eq!((link.subject.as_str(), link.availability, link.outages, link.mttr),
+               ("alpha-beta", Some(100.0), 0, None));
+
+    let csv = report.to_csv();
+    assert!(csv.contains("node,beta,75.000,240,60,2,40,30\n"));
+    assert!(csv.contains("link,alpha-beta,100.000,240,0,0,0,\n"));
+    assert!(report.to_html().contains("<td>beta</td><td>75.000%</td>"));
+}
+
+#[test]
+fn limits_report_to_period() {
+    let report = availability::report(&history(), 200, 1000);
+    let beta = &report.nodes[0];
+    assert_eq!((beta.observed, beta.downtime, beta.outages), (40, 20, 1));
+
+    let (from, to) = availability::month("2026-12").unwrap();
+    assert!(to - from >= 31 * 86400 - 3600 && to - from <= 31 * 86400 + 3600);
+    assert!(availability::month("2026-13").is_none());
+    assert!(availability::month("december").is_none());
+}
@@ -0,0 +1,61 @@
+#![allow(dead_code)]
+
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use tincmgr::domain::Data;
+use tincmgr::mock_tincd::{MockEdge, MockNode, MockTincd, Topology};
+use tincmgr::tinc_tcp_stream::TincStream;
+
+/// A fixed poll time, in the future of the test data.
+pub const TIME: i64 = 1_790_000_000;
+
+/// An empty directory in the system temp dir, removed again on drop.
+pub struct TempDir(PathBuf);
+impl Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub fn temp_dir(name: &str) -> TempDir {
+    let dir = std::env::temp_dir().join(format!("tincmgr-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    TempDir(dir)
+}
+
+/// alpha (the local node) - beta - gamma, with gamma unreachable and only
+/// announcing its side of the beta link.
+pub fn mesh() -> Topology {
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(MockNode::new("beta"))
+        .node(MockNode::new("gamma").unreachable())
+        .edge(MockEdge::new("alpha", "beta", 100))
+        .edge(MockEdge::new("beta", "alpha", 120))
+        .edge(MockEdge::new("gamma", "beta", 300))
+        .subnet("10.0.1.0/24#10", "alpha")
+        .subnet("10.0.2.0/24#10", "beta")
+        .subnet("10.0.3.0/24#10", "gamma")
+}
+
+/// Poll the mock the way the main loop polls tincd.
+pub fn load(mock: &MockTincd) -> Data {
+    let mut stream = TincStream::new(mock.pid_path()).unwrap();
+    let nodes = stream.dump_nodes().unwrap();
+    let edges = stream.dump_edges().unwrap();
+    let subnets = stream.dump_subnets().unwrap();
+    let mut data = Data::new(nodes, subnets, edges);
+    data.set_daemon(stream.identity().clone());
+    data.set_connections(&stream.dump_connections().unwrap());
+    data.set_traffic(&stream.dump_traffic().unwrap());
+    data
+}
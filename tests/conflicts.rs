@@ -0,0 +1,61 @@
+extern crate tincmgr;
+
+use tincmgr::domain::conflicts::{check_conflicts, ConflictKind};
+use tincmgr::domain::subnets::Subnet;
+
+fn subnets(list: &[(&str, &str)]) -> Vec<Subnet> {
+    let mut subnets: Vec<Subnet> = list.iter()
+        .map(|(net, owner)| Subnet::parse(net, owner).unwrap())
+        .collect();
+    subnets.sort();
+    subnets
+}
+
+fn kinds(list: &[(&str, &str)]) -> Vec<(ConflictKind, String, String)> {
+    check_conflicts(&subnets(list)).conflicts.into_iter()
+        .map(|x| (x.kind, x.subnet.owner.unwrap(), x.other.owner.unwrap()))
+        .collect()
+}
+
+#[test]
+fn detects_duplicates_and_weight_ties() {
+    assert_eq!(kinds(&[
+        ("10.0.1.0/24", "alpha"),
+        ("10.0.1.0/24", "beta"),
+        ("10.0.2.0/24#5", "alpha"),
+        ("10.0.2.0/24#20", "beta"),
+        ("00:16:3e:00:00:01", "alpha"),
+        ("00:16:3e:00:00:01", "gamma"),
+    ]), vec![
+        (ConflictKind::WeightTie, "alpha".to_string(), "beta".to_string()),
+        (ConflictKind::Duplicate, "alpha".to_string(), "beta".to_string()),
+        (ConflictKind::WeightTie, "alpha".to_string(), "gamma".to_string()),
+    ]);
+}
+
+#[test]
+fn detects_shadowed_and_overlapping_subnets() {
+    assert_eq!(kinds(&[
+        ("10.0.0.0/16", "alpha"),
+        ("10.0.3.0/24", "beta"),
+        ("10.0.0.0/24", "alpha"),
+        ("fd00::/48", "gamma"),
+        ("fd00:0:0:1::/64", "beta"),
+    ]), vec![
+        (ConflictKind::Overlap, "alpha".to_string(), "alpha".to_string()),
+        (ConflictKind::Shadowed, "alpha".to_string(), "beta".to_string()),
+        (ConflictKind::Shadowed, "gamma".to_string(), "beta".to_string()),
+    ]);
+}
+
+#[test]
+fn ignores_disjoint_and_broadcast_subnets() {
+    let report = check_conflicts(&subnets(&[
+        ("10.0.1.0/24", "alpha"),
+        ("10.0.2.0/24", "beta"),
+        ("224.0.0.0/4", "(broadcast)"),
+        ("224.0.0.0/4", "alpha"),
+    ]));
+    assert_eq!(report.checked, 3);
+    assert!(report.conflicts.is_empty());
+}
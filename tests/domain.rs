@@ -0,0 +1,193 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::control;
+use tincmgr::domain::info::NodeInfo;
+use tincmgr::domain::links::Endpoint;
+use tincmgr::domain::nodes::DataPath;
+use tincmgr::domain::subnets::{Net, Subnet};
+use tincmgr::mock_tincd::{MockConnection, MockEdge, MockNode, MockTincd, Topology};
+
+#[test]
+fn keeps_both_edge_directions() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+
+    assert_eq!(data.links.len(), 2);
+    let link = &data.links[0];
+    assert_eq!((link.sname.as_str(), link.tname.as_str()), ("alpha", "beta"));
+    assert_eq!(link.forward.as_ref().unwrap().weight, 100);
+    assert_eq!(link.reverse.as_ref().unwrap().weight, 120);
+    assert_eq!(link.weight, 110);
+    assert!(!link.one_sided);
+    assert_eq!(link.reachable, 1);
+
+    let link = &data.links[1];
+    assert_eq!((link.sname.as_str(), link.tname.as_str()), ("beta", "gamma"));
+    assert!(link.forward.is_none());
+    assert!(link.one_sided);
+    assert_eq!(link.target_state, Endpoint::Unreachable);
+    assert_eq!(link.reachable, 0);
+}
+
+#[test]
+fn models_unknown_endpoints() {
+    let mock = MockTincd::start(common::mesh()
+        .edge(MockEdge::new("beta", "delta", 50))).unwrap();
+    let data = common::load(&mock);
+
+    let link = data.links.iter().find(|l| l.tname == "delta").unwrap();
+    assert_eq!(link.target, None);
+    assert_eq!(link.target_state, Endpoint::Unknown);
+    assert_eq!(link.source_state, Endpoint::Reachable);
+}
+
+#[test]
+fn counts_node_edges_and_subnets() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+
+    let beta = data.nodes.iter().find(|n| n.name == "beta").unwrap();
+    assert_eq!(beta.edges, 2);
+    assert_eq!(beta.nets.iter().map(ToString::to_string).collect::<Vec<_>>(), vec!["10.0.2.0/24"]);
+}
+
+#[test]
+fn parses_typed_subnets() {
+    let mock = MockTincd::start(common::mesh()
+        .subnet("fd00::1", "beta")
+        .subnet("00:16:3E:00:00:01#5", "beta")
+        .subnet("ff:ff:ff:ff:ff:ff", "(broadcast)")
+        .subnet("10.0.2.0/33", "beta")).unwrap();
+    let data = common::load(&mock);
+
+    let beta = data.nodes.iter().find(|n| n.name == "beta").unwrap();
+    let nets: Vec<&Net> = beta.nets.iter().map(|s| &s.net).collect();
+    assert_eq!(nets, vec![
+        &Net::Ipv4 { address: "10.0.2.0".parse().unwrap(), prefix: 24 },
+        &Net::Ipv6 { address: "fd00::1".parse().unwrap(), prefix: 128 },
+        &Net::Mac { address: "00:16:3e:00:00:01".to_string() },
+    ]);
+    assert_eq!(beta.nets[0].weight, 10);
+    assert_eq!(beta.nets[2].weight, 5);
+    assert_eq!(beta.nets[2].to_string(), "00:16:3e:00:00:01#5");
+
+    let broadcast: Vec<&Subnet> = data.subnets.iter().filter(|s| s.is_broadcast()).collect();
+    assert_eq!(broadcast.len(), 1);
+    assert_eq!(broadcast[0].net.prefix(), 48);
+    assert_eq!(data.subnets.len(), 6);
+}
+
+#[test]
+fn marks_local_node() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+
+    let local: Vec<&str> = data.nodes.iter().filter(|n| n.local).map(|n| n.name.as_str()).collect();
+    assert_eq!(local, vec!["alpha"]);
+    assert_eq!(data.daemon.unwrap().name, "alpha");
+}
+
+#[test]
+fn aggregates_node_info() {
+    let mut delta = MockNode::new("delta");
+    delta.host = "192.0.2.4".to_string();
+    delta.port = "655".to_string();
+    delta.options = 0x0700_000e;
+    delta.minmtu = 1400;
+    delta.last_state_change = 1_600_000_000;
+    let mock = MockTincd::start(Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(delta)
+        .edge(MockEdge::new("alpha", "delta", 50))
+        .subnet("10.0.4.0/24", "delta")
+        .connection(MockConnection::new("delta", 7))
+        .traffic("delta", 1, 100, 2, 200)).unwrap();
+    let data = common::load(&mock);
+
+    let info = NodeInfo::from(&data, "delta").unwrap();
+    let node = &info.node;
+    assert_eq!((node.address.as_ref().unwrap().as_str(), node.port), ("192.0.2.4", Some(655)));
+    assert!(node.status.reachable && node.status.validkey && !node.status.udp_confirmed);
+    assert!(node.options.tcponly && node.options.pmtu_discovery && !node.options.indirect);
+    assert_eq!(node.options.protocol_minor, 7);
+    assert_eq!((node.pmtu, node.minmtu, node.maxmtu), (1518, 1400, 1518));
+    assert_eq!(node.last_state_change, 1_600_000_000);
+    assert_eq!(node.traffic.as_ref().unwrap().out_bytes, 200);
+    assert_eq!(node.nets.len(), 1);
+    assert_eq!(info.links.len(), 1);
+    assert_eq!(info.connection.unwrap().socket, 7);
+
+    assert!(NodeInfo::from(&data, "alpha").unwrap().connection.is_none());
+    assert!(NodeInfo::from(&data, "epsilon").is_none());
+}
+
+#[test]
+fn classifies_data_paths() {
+    let node = |name: &str, status: u32, options: u32, nexthop: &str, via: &str| {
+        let mut node = MockNode::new(name);
+        node.status = status;
+        node.options = options;
+        node.nexthop = nexthop.to_string();
+        node.via = via.to_string();
+        node
+    };
+    let mock = MockTincd::start(Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(node("udp", 0x92, 0x0700_000c, "udp", "udp"))
+        .node(node("tcponly", 0x92, 0x0700_000e, "tcponly", "tcponly"))
+        .node(node("fallback", 0x12, 0x0700_000c, "fallback", "fallback"))
+        .node(node("indirect", 0x92, 0x0700_000d, "udp", "udp"))
+        .node(node("behind", 0x12, 0x0700_000c, "udp", "behind"))
+        .node(node("neighbour", 0x12, 0x0700_000d, "neighbour", "alpha"))
+        .node(node("farther", 0x12, 0x0700_000d, "udp", "alpha"))
+        .node(node("nokey", 0x10, 0x0700_000c, "nokey", "nokey"))
+        .node(MockNode::new("down").unreachable())).unwrap();
+    let data = common::load(&mock);
+
+    let paths: Vec<(&str, DataPath, Option<&str>)> = data.nodes.iter()
+        .map(|n| (n.name.as_str(), n.data_path, n.relay.as_deref()))
+        .collect();
+    assert_eq!(paths, vec![
+        ("alpha", DataPath::Local, None),
+        ("udp", DataPath::Udp, None),
+        ("tcponly", DataPath::Tcp, None),
+        ("fallback", DataPath::Tcp, None),
+        ("indirect", DataPath::Relayed, Some("udp")),
+        ("behind", DataPath::Relayed, Some("udp")),
+        ("neighbour", DataPath::Tcp, None),
+        ("farther", DataPath::Relayed, Some("udp")),
+        ("nokey", DataPath::Pending, None),
+        ("down", DataPath::Unreachable, None),
+    ]);
+}
+
+#[test]
+fn decodes_meta_connections() {
+    let mut control_client = MockConnection::new("<control>", 9);
+    control_client.host = "127.0.0.1".to_string();
+    control_client.port = "41000".to_string();
+    control_client.status = 0x0200;
+    let mut beta = MockConnection::new("beta", 5);
+    beta.status = 0x01c1;
+    beta.options = 0x0700_0008;
+    let mock = MockTincd::start(common::mesh()
+        .connection(beta)
+        .connection(control_client)).unwrap();
+
+    let connections = control::dump_connections(mock.pid_path()).unwrap();
+    assert_eq!(connections.len(), 2);
+    let beta = &connections[0];
+    assert!(beta.is_meta());
+    assert!(beta.status.pinged && beta.status.encryptout && beta.status.decryptin && beta.status.mst);
+    assert!(!beta.status.connecting && !beta.status.control);
+    assert!(beta.options.clamp_mss && !beta.options.tcponly);
+    assert_eq!(beta.options.protocol_minor, 7);
+    let control_client = &connections[1];
+    assert!(!control_client.is_meta());
+    assert_eq!((control_client.address.as_deref(), control_client.port), (Some("127.0.0.1"), Some(41000)));
+
+    let data = common::load(&mock);
+    assert_eq!(data.connections, connections);
+}
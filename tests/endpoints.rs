@@ -0,0 +1,78 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::endpoints::EndpointTracker;
+use tincmgr::events::EventKind;
+use tincmgr::mock_tincd::{MockEdge, MockNode, MockTincd, Topology};
+
+fn node(name: &str, host: &str) -> MockNode {
+    let mut node = MockNode::new(name);
+    node.host = host.to_string();
+    node.port = "655".to_string();
+    node
+}
+
+fn edge(from: &str, to: &str, host: &str, local_host: &str) -> MockEdge {
+    let mut edge = MockEdge::new(from, to, 100);
+    edge.host = host.to_string();
+    edge.port = "655".to_string();
+    edge.local_host = local_host.to_string();
+    edge.local_port = "655".to_string();
+    edge
+}
+
+/// beta announces a private address but alpha sees a public one.
+fn nat(beta_host: &str) -> Topology {
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(node("beta", beta_host))
+        .node(node("carol", "203.0.113.3"))
+        .node(MockNode::new("dave"))
+        .edge(edge("alpha", "beta", beta_host, "192.0.2.1"))
+        .edge(edge("beta", "alpha", "192.0.2.1", "10.1.1.2"))
+        .edge(edge("alpha", "carol", "203.0.113.3", "192.0.2.1"))
+        .edge(edge("carol", "alpha", "192.0.2.1", "203.0.113.3"))
+}
+
+#[test]
+fn flags_nat_and_unknown_endpoints() {
+    let mock = MockTincd::start(nat("198.51.100.2")).unwrap();
+    let (report, events) = EndpointTracker::new().update(&common::load(&mock), 0);
+    assert!(events.is_empty());
+
+    let flags: Vec<(&str, bool, bool)> = report.nodes.iter()
+        .map(|x| (x.name.as_str(), x.behind_nat, x.unknown))
+        .collect();
+    assert_eq!(flags, vec![
+        ("alpha", false, false),
+        ("beta", true, false),
+        ("carol", false, false),
+        ("dave", false, true),
+    ]);
+    let beta = &report.nodes[1];
+    assert_eq!(beta.endpoint.as_ref().unwrap(), "198.51.100.2 port 655");
+    assert_eq!(beta.local_addresses, vec!["10.1.1.2"]);
+    assert_eq!(beta.seen_as, vec!["198.51.100.2"]);
+}
+
+#[test]
+fn records_endpoint_changes() {
+    let mock = MockTincd::start(nat("198.51.100.2")).unwrap();
+    let mut tracker = EndpointTracker::new();
+    tracker.update(&common::load(&mock), 10);
+    tracker.update(&common::load(&mock), 20);
+
+    mock.set_topology(nat("198.51.100.9"));
+    let (report, events) = tracker.update(&common::load(&mock), 30);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].node, "beta");
+    assert_eq!(events[0].kind, EventKind::EndpointChanged {
+        from: "198.51.100.2 port 655".to_string(),
+        to: "198.51.100.9 port 655".to_string(),
+    });
+    let history: Vec<(&str, i64, i64)> = report.nodes[1].history.iter()
+        .map(|x| (x.address.as_str(), x.first_seen, x.last_seen))
+        .collect();
+    assert_eq!(history, vec![("198.51.100.2 port 655", 10, 20), ("198.51.100.9 port 655", 30, 30)]);
+}
@@ -0,0 +1,98 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::domain::Data;
+use tincmgr::events::EventKind;
+use tincmgr::flaps::{FlapDetector, FlapSubject};
+use tincmgr::metrics;
+use tincmgr::mock_tincd::{MockEdge, MockNode, MockTincd, Topology};
+use tincmgr::state::State;
+
+fn beta_down() -> Topology {
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(MockNode::new("beta").unreachable())
+        .node(MockNode::new("gamma").unreachable())
+        .edge(MockEdge::new("gamma", "beta", 300))
+}
+
+/// Snapshots with beta and the alpha-beta link up, then down.
+fn snapshots() -> (Data, Data) {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let up = common::load(&mock);
+    mock.set_topology(beta_down());
+    (up, common::load(&mock))
+}
+
+#[test]
+fn reports_changes_until_flapping() {
+    let (up, down) = snapshots();
+    let mut detector = FlapDetector::new(100, 3);
+    assert!(detector.update(&up, 0).1.is_empty());
+
+    let (report, events) = detector.update(&down, 10);
+    let kinds: Vec<(&str, &EventKind)> = events.iter().map(|x| (x.node.as_str(), &x.kind)).collect();
+    assert_eq!(kinds, vec![
+        ("beta", &EventKind::Unreachable),
+        ("alpha", &EventKind::LinkRemoved { peer: "beta".to_string() }),
+    ]);
+    assert_eq!(report.states.len(), 2);
+    assert_eq!(report.flapping().count(), 0);
+
+    let events = detector.update(&up, 20).1;
+    assert_eq!(events[0].kind, EventKind::Reachable);
+    assert_eq!(events[1].kind, EventKind::LinkAdded { peer: "beta".to_string() });
+
+    let (report, events) = detector.update(&down, 30);
+    assert_eq!(events[0].kind, EventKind::FlapStarted { peer: None, score: 3 });
+    assert_eq!(events[1].kind, EventKind::FlapStarted { peer: Some("beta".to_string()), score: 3 });
+    let flapping: Vec<(FlapSubject, &str)> = report.flapping().map(|x| (x.subject, x.name.as_str())).collect();
+    assert_eq!(flapping, vec![(FlapSubject::Node, "beta"), (FlapSubject::Link, "alpha-beta")]);
+
+    let text = metrics::render(&State { flaps: report, ..State::default() });
+    assert!(text.contains("tincmgr_flap_score{subject=\"node\",name=\"beta\"} 3\n"));
+    assert!(text.contains("tincmgr_flapping 2\n"));
+}
+
+#[test]
+fn suppresses_changes_while_flapping() {
+    let (up, down) = snapshots();
+    let mut detector = FlapDetector::new(100, 3);
+    for (time, data) in [(0, &up), (10, &down), (20, &up), (30, &down)].iter() {
+        detector.update(data, *time);
+    }
+
+    let (report, events) = detector.update(&up, 40);
+    assert!(events.is_empty());
+    assert_eq!(report.states[0].suppressed, 1);
+    assert_eq!(report.states[0].score, 4);
+
+    // Still flapping while the changes are within the window.
+    assert!(detector.update(&up, 130).1.is_empty());
+    let (report, events) = detector.update(&up, 141);
+    assert_eq!(events[0].node, "beta");
+    assert_eq!(events[0].kind, EventKind::FlapStopped { peer: None, up: true, suppressed: 1 });
+    assert_eq!(events.len(), 2);
+    assert!(report.states.is_empty());
+}
+
+#[test]
+fn reports_subjects_appearing_after_the_first_update() {
+    let (up, down) = snapshots();
+    let mut detector = FlapDetector::new(100, 3);
+    assert!(detector.update(&down, 0).1.is_empty());
+
+    let events = detector.update(&up, 10).1;
+    let kinds: Vec<(&str, &EventKind)> = events.iter().map(|x| (x.node.as_str(), &x.kind)).collect();
+    assert_eq!(kinds, vec![
+        ("beta", &EventKind::Reachable),
+        ("alpha", &EventKind::LinkAdded { peer: "beta".to_string() }),
+    ]);
+
+    // A link forgotten after being down for a window is reported again.
+    assert_eq!(detector.update(&down, 20).1.len(), 2);
+    assert!(detector.update(&down, 200).1.is_empty());
+    let events = detector.update(&up, 210).1;
+    assert_eq!(events[1].kind, EventKind::LinkAdded { peer: "beta".to_string() });
+}
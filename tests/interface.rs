@@ -0,0 +1,81 @@
+extern crate tincmgr;
+
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use tincmgr::interface::{interface_name, InterfaceSeries, InterfaceStats};
+use tincmgr::tinc_conf;
+
+use common::{temp_dir, TempDir};
+
+fn fake_sysfs(name: &str, rx_bytes: u64, tx_bytes: u64) -> TempDir {
+    let sysfs = temp_dir(name);
+    let statistics = sysfs.join("vpn0").join("statistics");
+    fs::create_dir_all(&statistics).unwrap();
+    fs::write(sysfs.join("vpn0").join("mtu"), "1400\n").unwrap();
+    for (counter, value) in [("rx_bytes", rx_bytes), ("tx_bytes", tx_bytes), ("rx_packets", 10),
+                             ("tx_packets", 20), ("rx_errors", 1), ("tx_errors", 2),
+                             ("rx_dropped", 3), ("tx_dropped", 4)].iter() {
+        fs::write(statistics.join(counter), format!("{}\n", value)).unwrap();
+    }
+    sysfs
+}
+
+#[test]
+fn parses_tinc_conf() {
+    let variables = tinc_conf::parse_variables(
+        "# comment\nName = alpha\ninterface=vpn0\nConnectTo beta\n\n-----BEGIN ED25519 PUBLIC KEY-----\nx = y\n");
+    assert_eq!(variables, vec![
+        ("name".to_string(), "alpha".to_string()),
+        ("interface".to_string(), "vpn0".to_string()),
+        ("connectto".to_string(), "beta".to_string()),
+    ]);
+}
+
+#[test]
+fn finds_interface_name() {
+    let confdir = temp_dir("confdir");
+    assert_eq!(interface_name(&confdir, None), None);
+    assert_eq!(interface_name(&confdir, Some("office")).unwrap(), "office");
+
+    fs::write(confdir.join("tinc.conf"), "Name = alpha\nInterface = vpn0\n").unwrap();
+    assert_eq!(interface_name(&confdir, Some("office")).unwrap(), "vpn0");
+}
+
+#[test]
+fn finds_confdir_from_netname() {
+    assert_eq!(tinc_conf::netname(Path::new("/run/tinc.office.pid")).unwrap(), "office");
+    assert_eq!(tinc_conf::netname(Path::new("/var/run/tinc.pid")), None);
+    assert_eq!(tinc_conf::confdir(Some("office")), Path::new("/etc/tinc/office"));
+    assert_eq!(tinc_conf::confdir(None), Path::new("/etc/tinc"));
+}
+
+#[test]
+fn reads_sysfs_statistics() {
+    let sysfs = fake_sysfs("sysfs-read", 1000, 2000);
+    let stats = InterfaceStats::read(&sysfs, "vpn0", 100).unwrap();
+
+    assert_eq!(stats.mtu, 1400);
+    assert_eq!((stats.rx_bytes, stats.tx_bytes), (1000, 2000));
+    assert_eq!((stats.rx_errors, stats.tx_errors, stats.rx_dropped, stats.tx_dropped), (1, 2, 3, 4));
+    assert!(InterfaceStats::read(&sysfs, "missing0", 100).is_err());
+}
+
+#[test]
+fn computes_rates_between_samples() {
+    let mut series = InterfaceSeries::default();
+    let sysfs = fake_sysfs("sysfs-rate-1", 1000, 2000);
+    series.push(InterfaceStats::read(&sysfs, "vpn0", 100).unwrap());
+    let sysfs = fake_sysfs("sysfs-rate-2", 3000, 2500);
+    let sample = series.push(InterfaceStats::read(&sysfs, "vpn0", 120).unwrap()).clone();
+
+    assert_eq!(sample.rx_rate, Some(100.0));
+    assert_eq!(sample.tx_rate, Some(25.0));
+
+    let sysfs = fake_sysfs("sysfs-rate-3", 10, 10);
+    let sample = series.push(InterfaceStats::read(&sysfs, "vpn0", 140).unwrap()).clone();
+    assert_eq!(sample.rx_rate, None);
+    assert_eq!(series.samples.len(), 3);
+}
@@ -0,0 +1,68 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::latency::{self, LatencyMonitor, LatencySource};
+use tincmgr::metrics;
+use tincmgr::mock_tincd::{MockEdge, MockNode, MockTincd, Topology};
+use tincmgr::state::State;
+
+fn links(rtt: i32) -> Topology {
+    let mut alpha_beta = MockEdge::new("alpha", "beta", 100);
+    alpha_beta.avg_rtt = Some(rtt);
+    let mut beta_alpha = MockEdge::new("beta", "alpha", 120);
+    beta_alpha.avg_rtt = Some(rtt + 2);
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(MockNode::new("beta"))
+        .node(MockNode::new("carol"))
+        .edge(alpha_beta)
+        .edge(beta_alpha)
+        .edge(MockEdge::new("alpha", "carol", 250))
+        .edge(MockEdge::new("carol", "alpha", 250))
+}
+
+#[test]
+fn parses_edge_rtt() {
+    let mock = MockTincd::start(links(12)).unwrap();
+    let data = common::load(&mock);
+
+    let link = data.links.iter().find(|x| x.tname == "beta").unwrap();
+    assert_eq!(link.forward.as_ref().unwrap().avg_rtt, Some(12));
+    assert_eq!(link.reverse.as_ref().unwrap().avg_rtt, Some(14));
+    assert_eq!((link.weight, link.avg_rtt), (110, Some(13)));
+    let link = data.links.iter().find(|x| x.tname == "carol").unwrap();
+    assert_eq!((link.weight, link.avg_rtt), (250, None));
+}
+
+#[test]
+fn summarizes_latency_windows() {
+    let mock = MockTincd::start(links(12)).unwrap();
+    let mut monitor = LatencyMonitor::new(latency::parse_windows("1h,60").unwrap());
+    monitor.update(&common::load(&mock), 0);
+    mock.set_topology(links(39));
+    monitor.update(&common::load(&mock), 100);
+    mock.set_topology(links(19));
+    let report = monitor.update(&common::load(&mock), 130);
+
+    assert_eq!(report.windows, vec![60, 3600]);
+    let beta = report.links.iter().find(|x| x.tname == "beta").unwrap();
+    assert_eq!((beta.source, beta.rtt), (LatencySource::AvgRtt, 20.0));
+    let minute = &beta.stats[0];
+    assert_eq!((minute.samples, minute.min, minute.avg, minute.max, minute.p95), (2, 20.0, 30.0, 40.0, 40.0));
+    let hour = &beta.stats[1];
+    assert_eq!((hour.samples, hour.min, hour.max), (3, 13.0, 40.0));
+    let carol = report.links.iter().find(|x| x.tname == "carol").unwrap();
+    assert_eq!((carol.source, carol.rtt), (LatencySource::Weight, 25.0));
+
+    let text = metrics::render(&State { latency: report, ..State::default() });
+    assert!(text.contains("tincmgr_link_rtt_milliseconds{sname=\"alpha\",tname=\"beta\"} 20\n"));
+}
+
+#[test]
+fn parses_latency_windows() {
+    assert_eq!(latency::parse_windows("5m,1h,1d"), Some(vec![300, 3600, 86400]));
+    assert_eq!(latency::parse_windows("30, 30s"), Some(vec![30]));
+    assert_eq!(latency::parse_windows("0m"), None);
+    assert_eq!(latency::parse_windows("1w"), None);
+}
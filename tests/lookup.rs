@@ -0,0 +1,66 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::domain::lookup::lookup;
+use tincmgr::domain::subnets::Net;
+use tincmgr::mock_tincd::{MockNode, MockTincd};
+
+fn address(addr: &str) -> Net {
+    Net::parse_address(addr).unwrap()
+}
+
+#[test]
+fn prefers_longest_prefix_then_lowest_weight() {
+    let mock = MockTincd::start(common::mesh()
+        .subnet("10.0.0.0/16", "alpha")
+        .subnet("10.0.2.128/25#20", "alpha")
+        .subnet("10.0.2.128/25#5", "beta")).unwrap();
+    let data = common::load(&mock);
+
+    let found = lookup(&data, &address("10.0.2.200")).unwrap();
+    assert_eq!(found.owner.as_ref().unwrap(), "beta");
+    assert_eq!(found.subnet.to_string(), "10.0.2.128/25#5");
+    assert!(found.reachable);
+    assert_eq!(found.nexthop.as_ref().unwrap(), "beta");
+    let matches: Vec<String> = found.matches.iter().map(ToString::to_string).collect();
+    assert_eq!(matches, vec!["10.0.2.128/25#5", "10.0.2.128/25#20", "10.0.2.0/24", "10.0.0.0/16"]);
+
+    assert_eq!(lookup(&data, &address("10.0.9.1")).unwrap().owner.unwrap(), "alpha");
+    assert!(lookup(&data, &address("192.168.1.1")).is_none());
+}
+
+#[test]
+fn skips_unreachable_owners() {
+    let mock = MockTincd::start(common::mesh()
+        .subnet("10.0.3.0/25", "beta")
+        .subnet("10.0.3.0/26", "gamma")
+        .node(MockNode::new("dave").unreachable())
+        .subnet("10.0.3.128/25", "dave")).unwrap();
+    let data = common::load(&mock);
+
+    let found = lookup(&data, &address("10.0.3.1")).unwrap();
+    assert_eq!(found.owner.unwrap(), "beta");
+
+    // With no reachable owner tincd falls back to the last match.
+    let found = lookup(&data, &address("10.0.3.200")).unwrap();
+    assert_eq!(found.matches.len(), 2);
+    assert_eq!(found.owner.unwrap(), "gamma");
+    assert_eq!(found.subnet.to_string(), "10.0.3.0/24");
+    assert!(!found.reachable);
+    assert_eq!(found.via, None);
+}
+
+#[test]
+fn matches_mac_and_broadcast_subnets() {
+    let mock = MockTincd::start(common::mesh()
+        .subnet("00:16:3e:00:00:01", "beta")
+        .subnet("ff:ff:ff:ff:ff:ff", "(broadcast)")).unwrap();
+    let data = common::load(&mock);
+
+    assert_eq!(lookup(&data, &address("00:16:3E:00:00:01")).unwrap().owner.unwrap(), "beta");
+    let found = lookup(&data, &address("ff:ff:ff:ff:ff:ff")).unwrap();
+    assert_eq!(found.owner, None);
+    assert!(found.reachable);
+    assert!(Net::parse_address("10.0.0.0/8").is_none());
+}
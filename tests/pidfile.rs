@@ -0,0 +1,89 @@
+extern crate tincmgr;
+
+mod common;
+
+use std::fs;
+use std::io::ErrorKind;
+use std::net::TcpListener;
+use std::process::Command;
+use std::time::Duration;
+
+use tincmgr::mock_tincd::MockTincd;
+use tincmgr::pidfile::{self, DaemonState, PidFile};
+use tincmgr::tinc_tcp_stream::TincStream;
+
+fn closed_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+fn dead_pid() -> u32 {
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+    child.id()
+}
+
+#[test]
+fn reads_mock_pidfile() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let pidfile = PidFile::read(mock.pid_path()).unwrap();
+
+    assert_eq!(pidfile.pid, mock.pid() as i32);
+    assert_eq!(pidfile.daemon_state(), DaemonState::Running);
+    assert_eq!(pidfile.cookie, mock.cookie());
+    assert_eq!(pidfile.address, "127.0.0.1");
+    assert_eq!(pidfile.port, mock.port().to_string());
+}
+
+#[test]
+fn reports_dead_daemon_as_stale() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let pid = dead_pid();
+    fs::write(mock.pidfile(), format!("{} {} 127.0.0.1 port {}\n", pid, mock.cookie(), closed_port())).unwrap();
+
+    let pidfile = PidFile::read(mock.pid_path()).unwrap();
+    assert_eq!(pidfile.daemon_state(), DaemonState::NotRunning);
+    let err = TincStream::new(mock.pid_path()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.to_string().contains("is not running"));
+}
+
+#[test]
+fn reports_reused_pid_as_stale() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    fs::write(mock.pidfile(), format!("{} {} 127.0.0.1 port {}\n", std::process::id(), mock.cookie(), closed_port())).unwrap();
+
+    let name = pidfile::process_name(std::process::id() as i32).unwrap();
+    assert!(name.starts_with("pidfile"));
+    let err = TincStream::new(mock.pid_path()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.to_string().contains("not tincd"));
+}
+
+#[test]
+fn reports_stale_pidfile_when_the_port_is_reused() {
+    // Something listens on the port, but it is not the dead tincd the
+    // pidfile and its cookie belong to.
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    fs::write(mock.pidfile(), format!("{} 0000 127.0.0.1 port {}\n", dead_pid(), mock.port())).unwrap();
+
+    let err = TincStream::new(mock.pid_path()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert!(err.to_string().contains("is not running"));
+}
+
+#[test]
+fn rejects_pidfile_without_port() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    fs::write(mock.pidfile(), "1234 cookie\n").unwrap();
+
+    assert_eq!(PidFile::read(mock.pid_path()).err().unwrap().kind(), ErrorKind::InvalidData);
+}
+
+#[test]
+fn watches_pidfile_rewrites() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let changes = pidfile::watch(mock.pid_path()).unwrap();
+
+    fs::write(mock.pidfile(), "1234 cookie 127.0.0.1 port 655\n").unwrap();
+    assert!(changes.recv_timeout(Duration::from_secs(5)).is_ok());
+}
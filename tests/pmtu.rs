@@ -0,0 +1,57 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::metrics;
+use tincmgr::mock_tincd::{MockNode, MockTincd, Topology};
+use tincmgr::pmtu::PmtuMonitor;
+use tincmgr::state::State;
+
+fn peers(beta_pmtu: i32) -> Topology {
+    let mut beta = MockNode::new("beta");
+    beta.pmtu = beta_pmtu;
+    beta.minmtu = beta_pmtu;
+    beta.maxmtu = beta_pmtu;
+    let mut carol = MockNode::new("carol");
+    carol.pmtu = 576;
+    carol.minmtu = 576;
+    carol.maxmtu = 1518;
+    let mut dave = MockNode::new("dave");
+    dave.options |= 0x2;
+    dave.pmtu = 0;
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(beta)
+        .node(carol)
+        .node(dave)
+}
+
+#[test]
+fn reports_unconverged_and_small_pmtu() {
+    let mock = MockTincd::start(peers(1200)).unwrap();
+    let report = PmtuMonitor::new(1280).update(&common::load(&mock), 0);
+
+    // alpha is local and dave is TCP only.
+    let names: Vec<&str> = report.nodes.iter().map(|x| x.name.as_str()).collect();
+    assert_eq!(names, vec!["beta", "carol"]);
+    assert_eq!(report.below_threshold().map(|x| x.name.as_str()).collect::<Vec<_>>(), vec!["beta"]);
+    assert_eq!(report.unconverged().map(|x| x.name.as_str()).collect::<Vec<_>>(), vec!["carol"]);
+}
+
+#[test]
+fn records_pmtu_changes() {
+    let mock = MockTincd::start(peers(1518)).unwrap();
+    let mut monitor = PmtuMonitor::new(1280);
+    assert!(monitor.update(&common::load(&mock), 10).changes.is_empty());
+
+    mock.set_topology(peers(1400));
+    let report = monitor.update(&common::load(&mock), 20);
+    assert_eq!(report.changes.len(), 1);
+    let change = &report.changes[0];
+    assert_eq!((change.name.as_str(), change.from, change.to, change.time), ("beta", 1518, 1400, 20));
+
+    let text = metrics::render(&State { pmtu: report, ..State::default() });
+    assert!(text.contains("tincmgr_node_pmtu{node=\"beta\"} 1400\n"));
+    assert!(text.contains("tincmgr_node_pmtu_converged{node=\"carol\"} 0\n"));
+    assert!(text.contains("tincmgr_pmtu_below_threshold 0\n"));
+}
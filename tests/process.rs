@@ -0,0 +1,45 @@
+extern crate tincmgr;
+
+use tincmgr::metrics;
+use tincmgr::process::ProcessStats;
+use tincmgr::state::State;
+
+#[test]
+fn samples_own_process() {
+    let pid = std::process::id() as i32;
+    let first = ProcessStats::sample(pid, None).unwrap();
+    assert_eq!(first.pid, pid);
+    assert!(first.rss_bytes > 0);
+    assert!(first.threads >= 1);
+    assert!(first.open_fds.unwrap() >= 3);
+    assert!(first.uptime_secs >= 0.0);
+    assert_eq!(first.cpu_percent, None);
+
+    let mut spin = 0u64;
+    for i in 0..20_000_000u64 {
+        spin = spin.wrapping_add(i);
+    }
+    assert!(spin > 0);
+    let second = ProcessStats::sample(pid, Some(&first)).unwrap();
+    assert!(second.cpu_secs() >= first.cpu_secs());
+    if second.sampled_at > first.sampled_at {
+        assert!(second.cpu_percent.unwrap() >= 0.0);
+    }
+}
+
+#[test]
+fn fails_for_missing_process() {
+    assert!(ProcessStats::sample(-1, None).is_err());
+}
+
+#[test]
+fn renders_process_metrics() {
+    let state = State {
+        process: Some(ProcessStats { pid: 42, rss_bytes: 4096, threads: 1, ..ProcessStats::default() }),
+        ..State::default()
+    };
+    let text = metrics::render(&state);
+    assert!(text.contains("tincmgr_attached 1\n"));
+    assert!(text.contains("tincd_resident_memory_bytes{pid=\"42\"} 4096\n"));
+    assert!(text.contains("tincd_cpu_seconds_total{pid=\"42\",mode=\"user\"} 0\n"));
+}
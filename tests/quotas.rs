@@ -0,0 +1,103 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::accounting::{Period, TrafficAccounting};
+use tincmgr::events::EventKind;
+use tincmgr::metrics;
+use tincmgr::mock_tincd::{MockNode, MockTincd, Topology};
+use tincmgr::quotas::{self, Quota, QuotaConfig, QuotaMonitor};
+use tincmgr::state::State;
+
+use common::TIME;
+
+fn counters(out_bytes: u64) -> Topology {
+    Topology::new("alpha")
+        .node(MockNode::new("alpha"))
+        .node(MockNode::new("beta"))
+        .node(MockNode::new("carol"))
+        .traffic("beta", 0, 0, out_bytes / 100, out_bytes)
+}
+
+#[test]
+fn parses_quota_config() {
+    let config = QuotaConfig::parse("\
+        WarnAt = 90, 50%\n\
+        MonthlyQuota = 100G\n\
+        Node = branch1\n\
+        DailyQuota = 1.5K\n\
+        MaxRate = 1M\n").unwrap();
+    assert_eq!(config.warn_levels, vec![50, 90]);
+    assert_eq!(config.quota("branch1"), Quota {
+        daily: Some(1536),
+        monthly: Some(100 << 30),
+        max_rate: Some(1 << 20),
+    });
+    assert_eq!(config.quota("branch2"), Quota { monthly: Some(100 << 30), ..Quota::default() });
+
+    assert_eq!(quotas::parse_bytes("2048"), Some(2048));
+    assert!(QuotaConfig::parse("DailyQuota = lots\n").is_err());
+    assert!(QuotaConfig::parse("Quota = 1G\n").is_err());
+}
+
+#[test]
+fn warns_at_each_level_once() {
+    let config = QuotaConfig::parse("Node = beta\nDailyQuota = 1000\nMaxRate = 5\n").unwrap();
+    let mock = MockTincd::start(counters(0)).unwrap();
+    let mut accounting = TrafficAccounting::default();
+    let mut monitor = QuotaMonitor::new(config);
+    let mut poll = |time: i64| {
+        let data = common::load(&mock);
+        accounting.update(&data, time).unwrap();
+        monitor.update(&data, &accounting, time)
+    };
+    assert!(poll(TIME).1.is_empty());
+
+    mock.set_topology(counters(850));
+    let (report, events) = poll(TIME + 60);
+    let kinds: Vec<&EventKind> = events.iter().map(|x| &x.kind).collect();
+    assert_eq!(kinds, vec![
+        &EventKind::QuotaWarning { period: Period::Day, level: 80, used: 850, limit: 1000 },
+        &EventKind::RateExceeded { rate: 14, limit: 5 },
+    ]);
+    assert_eq!(report.nodes.len(), 1);
+    assert!(report.nodes[0].over_rate && !report.nodes[0].over_quota);
+
+    // Still at 85%.
+    assert!(poll(TIME + 90).1.is_empty());
+
+    mock.set_topology(counters(1050));
+    let (report, events) = poll(TIME + 150);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, EventKind::QuotaWarning { period: Period::Day, level: 100, used: 1050, limit: 1000 });
+    let beta = &report.nodes[0];
+    assert!(beta.over_quota && !beta.over_rate);
+    assert_eq!(beta.daily.as_ref().unwrap().level, Some(100));
+
+    let text = metrics::render(&State { quotas: report, ..State::default() });
+    assert!(text.contains("tincmgr_node_quota_used_ratio{node=\"beta\",period=\"day\"} 1.05\n"));
+    assert!(text.contains("tincmgr_nodes_over_quota 1\n"));
+}
+
+#[test]
+fn does_not_repeat_warnings_after_a_restart() {
+    let config = QuotaConfig::parse("DailyQuota = 1000\n").unwrap();
+    let mock = MockTincd::start(counters(0)).unwrap();
+    let mut accounting = TrafficAccounting::default();
+    accounting.update(&common::load(&mock), TIME).unwrap();
+    mock.set_topology(counters(850));
+    accounting.update(&common::load(&mock), TIME + 60).unwrap();
+
+    // A new monitor, like after a restart, takes the 80% as already warned.
+    let mut monitor = QuotaMonitor::new(config);
+    let (report, events) = monitor.update(&common::load(&mock), &accounting, TIME + 80);
+    assert!(events.is_empty());
+    assert_eq!(report.nodes[0].daily.as_ref().unwrap().level, Some(80));
+
+    mock.set_topology(counters(1050));
+    let data = common::load(&mock);
+    accounting.update(&data, TIME + 100).unwrap();
+    let events = monitor.update(&data, &accounting, TIME + 100).1;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, EventKind::QuotaWarning { period: Period::Day, level: 100, used: 1050, limit: 1000 });
+}
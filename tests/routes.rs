@@ -0,0 +1,99 @@
+extern crate tincmgr;
+
+mod common;
+
+use std::fs;
+use std::net::IpAddr;
+
+use tincmgr::mock_tincd::MockTincd;
+use tincmgr::routes::{check_routes, read_kernel_routes, KernelRoute, RouteProblemKind};
+
+use common::{temp_dir, TempDir};
+
+const ROUTE: &str = "\
+Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t010200C0\t0003\t0\t0\t0\t00000000\t0\t0\t0
+vpn0\t0000000A\t00000000\t0001\t0\t0\t0\t0000FFFF\t0\t0\t0
+eth0\t0003000A\t00000000\t0001\t0\t0\t0\t00FFFFFF\t0\t0\t0
+";
+
+const IPV6_ROUTE: &str = "\
+fd000000000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001     vpn0
+00000000000000000000000000000001 80 00000000000000000000000000000000 00 00000000000000000000000000000000 00000000 00000001 00000000 80200001       lo
+";
+
+fn proc_net(name: &str) -> TempDir {
+    let dir = temp_dir(name);
+    fs::write(dir.join("route"), ROUTE).unwrap();
+    fs::write(dir.join("ipv6_route"), IPV6_ROUTE).unwrap();
+    dir
+}
+
+fn route(interface: &str, destination: &str, prefix: u8) -> KernelRoute {
+    KernelRoute {
+        interface: interface.to_string(),
+        destination: destination.parse::<IpAddr>().unwrap(),
+        prefix,
+    }
+}
+
+#[test]
+fn reads_proc_net_routes() {
+    let routes = read_kernel_routes(&proc_net("proc-net-read")).unwrap();
+
+    assert_eq!(routes, vec![
+        route("eth0", "0.0.0.0", 0),
+        route("vpn0", "10.0.0.0", 16),
+        route("eth0", "10.0.3.0", 24),
+        route("vpn0", "fd00::", 64),
+    ]);
+}
+
+#[test]
+fn reports_unrouted_subnets() {
+    let mock = MockTincd::start(common::mesh()
+        .subnet("10.0.2.7", "beta")
+        .subnet("fd00::/64#5", "beta")
+        .subnet("fd01::/64", "beta")
+        .subnet("10.0.3.128/25", "beta")
+        .subnet("192.168.9.0/24", "beta")
+        .subnet("00:11:22:33:44:55", "beta")).unwrap();
+    let data = common::load(&mock);
+    let routes = read_kernel_routes(&proc_net("proc-net-check")).unwrap();
+
+    let report = check_routes(&data, "vpn0", &routes);
+    assert_eq!(report.checked, 6);
+    let problems: Vec<(String, &RouteProblemKind)> = report.problems.iter()
+        .map(|x| (x.subnet.to_string(), &x.kind))
+        .collect();
+    assert_eq!(problems, vec![
+        ("10.0.3.128/25".to_string(), &RouteProblemKind::WrongInterface { route: route("eth0", "10.0.3.0", 24) }),
+        ("192.168.9.0/24".to_string(), &RouteProblemKind::Missing),
+        ("fd01::/64".to_string(), &RouteProblemKind::Missing),
+    ]);
+}
+
+#[test]
+fn skips_unreachable_owners() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+
+    // gamma is down, its subnet-down script took the route to 10.0.3.0/24.
+    let report = check_routes(&data, "vpn0", &[]);
+    assert_eq!(report.checked, 1);
+    assert_eq!(report.problems.len(), 1);
+    assert_eq!(report.problems[0].subnet.to_string(), "10.0.2.0/24");
+    assert!(!report.problems.iter().any(|x| x.subnet.to_string() == "10.0.3.0/24"));
+}
+
+#[test]
+fn reports_shadowed_subnets() {
+    let mock = MockTincd::start(common::mesh()
+        .subnet("10.0.0.0/8", "beta")).unwrap();
+    let data = common::load(&mock);
+    let routes = vec![route("vpn0", "10.0.0.0", 8), route("eth0", "10.0.3.0", 24)];
+
+    let report = check_routes(&data, "vpn0", &routes);
+    assert!(report.problems.iter().any(|x| x.subnet.to_string() == "10.0.0.0/8"
+        && x.kind == RouteProblemKind::Shadowed { route: route("eth0", "10.0.3.0", 24) }));
+}
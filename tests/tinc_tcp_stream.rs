@@ -0,0 +1,133 @@
+extern crate tincmgr;
+
+mod common;
+
+use std::fs;
+use std::io::{ErrorKind, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use tincmgr::mock_tincd::{Fault, MockConnection, MockTincd};
+use tincmgr::tinc_tcp_stream::{RequestType, TincStream};
+
+#[test]
+fn dumps_scripted_topology() {
+    let mock = MockTincd::start(common::mesh()
+        .connection(MockConnection::new("beta", 5))
+        .traffic("beta", 10, 1500, 20, 3000)).unwrap();
+    let mut stream = TincStream::new(mock.pid_path()).unwrap();
+
+    let nodes = stream.dump_nodes().unwrap();
+    assert_eq!(nodes.iter().map(|n| n.node.as_str()).collect::<Vec<_>>(), vec!["alpha", "beta", "gamma"]);
+    assert_eq!(stream.dump_edges().unwrap().len(), 3);
+    let subnets = stream.dump_subnets().unwrap();
+    assert_eq!(subnets[1].addr, "10.0.2.0/24#10");
+    assert_eq!(subnets[1].name, "beta");
+    let connections = stream.dump_connections().unwrap();
+    assert_eq!(connections[0].node, "beta");
+    assert_eq!(connections[0].socket, "5");
+    let traffic = stream.dump_traffic().unwrap();
+    assert_eq!(traffic.len(), 1);
+    assert_eq!((traffic[0].node.as_str(), traffic[0].out_bytes.as_str()), ("beta", "3000"));
+}
+
+#[test]
+fn skips_malformed_lines() {
+    let mock = MockTincd::start(common::mesh()
+        .raw(RequestType::ReqDumpNodes, "18 3 broken line")
+        .raw(RequestType::ReqDumpEdges, "18 4")).unwrap();
+    let mut stream = TincStream::new(mock.pid_path()).unwrap();
+
+    assert_eq!(stream.dump_nodes().unwrap().len(), 3);
+    assert_eq!(stream.dump_edges().unwrap().len(), 3);
+}
+
+#[test]
+fn waits_for_slow_answers() {
+    let mock = MockTincd::start(common::mesh()
+        .fault(RequestType::ReqDumpNodes, Fault::Delay(Duration::from_millis(100)))).unwrap();
+    let mut stream = TincStream::new(mock.pid_path()).unwrap();
+
+    assert_eq!(stream.dump_nodes().unwrap().len(), 3);
+}
+
+#[test]
+fn fails_when_daemon_disconnects() {
+    let mock = MockTincd::start(common::mesh()
+        .fault(RequestType::ReqDumpSubnets, Fault::Disconnect)).unwrap();
+    let mut stream = TincStream::new(mock.pid_path()).unwrap();
+
+    assert!(stream.dump_nodes().is_ok());
+    assert!(stream.dump_subnets().is_err());
+}
+
+#[test]
+fn fails_with_wrong_cookie() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let pidfile = fs::read_to_string(mock.pidfile()).unwrap()
+        .replace(mock.cookie(), "0000");
+    fs::write(mock.pidfile(), pidfile).unwrap();
+
+    let err = TincStream::new(mock.pid_path()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+    assert!(err.to_string().contains("rejected the control cookie"));
+}
+
+#[test]
+fn hangs_up_silently_on_wrong_cookie() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let mut stream = TcpStream::connect(("127.0.0.1", mock.port())).unwrap();
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    stream.write_all(b"0 ^0000 0\n").unwrap();
+
+    let mut answer = String::new();
+    stream.read_to_string(&mut answer).unwrap();
+    assert_eq!(answer, "");
+}
+
+#[test]
+fn learns_daemon_identity() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let stream = TincStream::new(mock.pid_path()).unwrap();
+
+    let identity = stream.identity();
+    assert_eq!(identity.name, "alpha");
+    assert_eq!((identity.protocol_major, identity.protocol_minor), (17, 7));
+    assert_eq!(identity.ctl_version, 0);
+    assert_eq!(identity.pid, mock.pid() as i32);
+}
+
+#[test]
+fn fails_with_malformed_greeting() {
+    let mut topology = common::mesh();
+    topology.protocol = String::new();
+    let mock = MockTincd::start(topology).unwrap();
+
+    let err = TincStream::new(mock.pid_path()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("Invalid greeting"));
+}
+
+#[test]
+fn fails_with_unsupported_control_version() {
+    let mut topology = common::mesh();
+    topology.ctl_version = 1;
+    let mock = MockTincd::start(topology).unwrap();
+
+    let err = TincStream::new(mock.pid_path()).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert!(err.to_string().contains("version 1"));
+}
+
+#[test]
+fn answers_control_requests() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let mut stream = TincStream::new(mock.pid_path()).unwrap();
+
+    stream.reload().unwrap();
+    stream.purge().unwrap();
+    stream.set_debug(3).unwrap();
+    stream.retry().unwrap();
+    assert!(stream.connect().is_err());
+    assert_eq!(mock.requests(), vec!["18 1", "18 8", "18 9 3", "18 10", "18 11"]);
+}
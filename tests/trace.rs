@@ -0,0 +1,64 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::domain::trace::trace;
+use tincmgr::mock_tincd::{MockEdge, MockNode, MockTincd, Topology};
+
+fn node(name: &str, distance: i32, nexthop: &str, via: &str) -> MockNode {
+    let mut node = MockNode::new(name);
+    node.distance = distance;
+    node.nexthop = nexthop.to_string();
+    node.via = via.to_string();
+    node
+}
+
+/// alpha reaches delta through beta, although the path through carol is
+/// lighter.
+fn diamond() -> Topology {
+    let mut topology = Topology::new("alpha")
+        .node(node("alpha", 0, "alpha", "alpha"))
+        .node(node("beta", 1, "beta", "beta"))
+        .node(node("carol", 1, "carol", "carol"))
+        .node(node("delta", 2, "beta", "beta"))
+        .subnet("10.0.4.0/24", "delta");
+    for (from, to, weight) in [("alpha", "beta", 100), ("beta", "delta", 100),
+                               ("alpha", "carol", 10), ("carol", "delta", 10)].iter() {
+        topology = topology
+            .edge(MockEdge::new(from, to, *weight))
+            .edge(MockEdge::new(to, from, *weight));
+    }
+    topology
+}
+
+#[test]
+fn rebuilds_route_from_nexthops() {
+    let mock = MockTincd::start(diamond()).unwrap();
+    let data = common::load(&mock);
+
+    let trace = trace(&data, "delta").unwrap();
+    assert_eq!(trace.source, "alpha");
+    assert_eq!(trace.via.as_ref().unwrap(), "beta");
+    let route = trace.route.unwrap();
+    assert_eq!(route.hops, vec!["alpha", "beta", "delta"]);
+    assert_eq!(route.weight, 200);
+    let shortest = trace.shortest.unwrap();
+    assert_eq!(shortest.hops, vec!["alpha", "carol", "delta"]);
+    assert_eq!(shortest.weight, 20);
+
+    let trace = self::trace(&data, "10.0.4.9").unwrap();
+    assert_eq!(trace.destination, "delta");
+    assert!(self::trace(&data, "10.0.9.9").is_none());
+}
+
+#[test]
+fn has_no_route_to_unreachable_nodes() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+
+    let trace = trace(&data, "gamma").unwrap();
+    assert!(!trace.reachable);
+    assert_eq!(trace.route, None);
+    assert_eq!(trace.shortest, None);
+    assert_eq!(self::trace(&data, "beta").unwrap().route.unwrap().hops, vec!["alpha", "beta"]);
+}
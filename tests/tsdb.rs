@@ -0,0 +1,68 @@
+extern crate tincmgr;
+
+mod common;
+
+use tincmgr::mock_tincd::MockTincd;
+use tincmgr::tsdb::{self, Resolution, SeriesId, Tsdb};
+
+use common::temp_dir;
+
+#[test]
+fn downsamples_finished_buckets() {
+    let id = SeriesId::new("node_pmtu", "beta");
+    let mut tsdb = Tsdb::default();
+    for minute in 0..62 {
+        tsdb.record(minute * 60, &[(id.clone(), minute as f64)]).save().unwrap();
+    }
+
+    let raw = tsdb.query(&id, 0, 3660, None).unwrap();
+    assert_eq!((raw.resolution, raw.points.len()), (Resolution::Raw, 62));
+
+    // The bucket at 3600 is still filling.
+    let five = tsdb.query(&id, 0, 3660, Some(Resolution::FiveMinutes)).unwrap();
+    assert_eq!(five.points.len(), 12);
+    let first = &five.points[0];
+    assert_eq!((first.time, first.value, first.min, first.max), (0, 2.0, 0.0, 4.0));
+    assert_eq!(five.points[11].time, 3300);
+
+    let hourly = tsdb.query(&id, 0, 3660, Some(Resolution::Hourly)).unwrap();
+    assert_eq!(hourly.points.len(), 1);
+    let hour = &hourly.points[0];
+    assert_eq!((hour.time, hour.value, hour.min, hour.max), (0, 29.5, 0.0, 59.0));
+
+    // A day later the raw points are gone, the buckets are not.
+    tsdb.record(86400 + 3660, &[(id.clone(), 0.0)]).save().unwrap();
+    assert_eq!(tsdb.query(&id, 0, 3660, Some(Resolution::Raw)).unwrap().points.len(), 0);
+    assert_eq!(tsdb.query(&id, 0, 3660, None).unwrap().resolution, Resolution::FiveMinutes);
+    assert_eq!(tsdb.query(&id, 0, 3660, Some(Resolution::FiveMinutes)).unwrap().points.len(), 13);
+    assert!(tsdb.query(&SeriesId::new("node_pmtu", "carol"), 0, 3660, None).is_none());
+}
+
+#[test]
+fn persists_poll_samples() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let samples = tsdb::samples(&common::load(&mock));
+    let value = |metric: &str, subject: &str| samples.iter()
+        .find(|(id, _)| *id == SeriesId::new(metric, subject))
+        .map(|(_, value)| *value);
+    assert_eq!(value("node_reachable", "beta"), Some(1.0));
+    assert_eq!(value("node_reachable", "gamma"), Some(0.0));
+    assert_eq!(value("node_reachable", "alpha"), None);
+    assert_eq!(value("link_weight", "alpha-beta"), Some(110.0));
+    assert_eq!(value("nodes_reachable", ""), Some(2.0));
+
+    let dir = temp_dir("tsdb");
+    {
+        let mut tsdb = Tsdb::open(&dir).unwrap();
+        // The first record compacts, the later ones append.
+        tsdb.record(100, &samples).save().unwrap();
+        tsdb.record(400, &samples).save().unwrap();
+    }
+    let tsdb = Tsdb::open(&dir).unwrap();
+    assert_eq!(tsdb.series().len(), samples.len());
+    let id = SeriesId::new("link_weight", "alpha-beta");
+    let raw = tsdb.query(&id, 0, 500, None).unwrap();
+    assert_eq!(raw.points.iter().map(|x| x.time).collect::<Vec<_>>(), vec![100, 400]);
+    let five = tsdb.query(&id, 0, 500, Some(Resolution::FiveMinutes)).unwrap();
+    assert_eq!((five.points.len(), five.points[0].value), (1, 110.0));
+}
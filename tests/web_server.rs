@@ -0,0 +1,149 @@
+extern crate actix_web;
+extern crate tincmgr;
+
+mod common;
+
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use actix_web::test::TestServer;
+use actix_web::HttpMessage;
+
+use tincmgr::domain::info::NodeInfo;
+use tincmgr::domain::lookup::Lookup;
+use tincmgr::mock_tincd::{MockEdge, MockNode, MockTincd};
+use tincmgr::purge::{PurgePolicy, PurgeReport, PurgeStatus, PurgedEdge, Purger};
+use tincmgr::state::{SharedState, State};
+use tincmgr::tinc_tcp_stream::DaemonIdentity;
+use tincmgr::tsdb::{Resolution, Series, SeriesId, Tsdb};
+use tincmgr::web_server::app;
+
+fn get(state: SharedState, path: &str) -> String {
+    let mut srv = TestServer::with_factory(move || app(state.clone()));
+    let request = srv.get().uri(srv.url(path)).finish().unwrap();
+    let response = srv.execute(request.send()).unwrap();
+    assert!(response.status().is_success());
+    let body = srv.execute(response.body()).unwrap();
+    String::from_utf8(body.to_vec()).unwrap()
+}
+
+#[test]
+fn purge_is_disabled_by_default() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+    let mut purger = Purger::new(PurgePolicy::parse("never").unwrap());
+
+    let status = purger.run(mock.pid_path(), &data).unwrap();
+    assert_eq!(status.interval, None);
+    assert_eq!(status.dry_run.nodes, vec!["gamma"]);
+    assert_eq!(status.dry_run.subnets[0].to_string(), "10.0.3.0/24");
+    assert!(status.history.is_empty());
+    assert!(!mock.requests().contains(&"18 8".to_string()));
+}
+
+#[test]
+fn purge_keeps_nodes_reachable_nodes_point_to() {
+    // beta still has an edge to dave, so tincd keeps dave and only drops
+    // its subnets and its own edges.
+    let mock = MockTincd::start(common::mesh()
+        .node(MockNode::new("dave").unreachable())
+        .edge(MockEdge::new("beta", "dave", 200))
+        .edge(MockEdge::new("dave", "beta", 200))
+        .subnet("10.0.4.0/24#10", "dave")).unwrap();
+    let report = PurgeReport::from(&common::load(&mock));
+
+    assert_eq!(report.nodes, vec!["gamma"]);
+    let subnets: Vec<String> = report.subnets.iter().map(|x| x.to_string()).collect();
+    assert_eq!(subnets, vec!["10.0.3.0/24", "10.0.4.0/24"]);
+    assert_eq!(report.edges.len(), 2);
+    assert!(report.edges.contains(&PurgedEdge { from: "gamma".to_string(), to: "beta".to_string() }));
+    assert!(report.edges.contains(&PurgedEdge { from: "dave".to_string(), to: "beta".to_string() }));
+}
+
+#[test]
+fn scheduled_purge_is_recorded() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+    let mut purger = Purger::new(PurgePolicy::Every(Duration::from_secs(0)));
+    let status = purger.run(mock.pid_path(), &data).unwrap();
+    assert!(mock.requests().contains(&"18 8".to_string()));
+    assert_eq!(status.history[0].purged.nodes, vec!["gamma"]);
+    assert!(status.dry_run.is_empty());
+}
+
+#[test]
+fn serves_purge_status() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let data = common::load(&mock);
+    let status = Purger::new(PurgePolicy::Disabled).run(mock.pid_path(), &data).unwrap();
+    let state = Arc::new(RwLock::new(State { purge: status, ..State::default() }));
+
+    let body = get(state, "/api/purge");
+    let status: PurgeStatus = serde_json::from_str(&body).unwrap();
+    assert_eq!(status.dry_run.nodes, vec!["gamma"]);
+}
+
+#[test]
+fn serves_daemon_identity() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let state = Arc::new(RwLock::new(State { data: Some(common::load(&mock)), ..State::default() }));
+
+    let body = get(state, "/api/daemon");
+    let daemon: DaemonIdentity = serde_json::from_str(&body).unwrap();
+    assert_eq!(daemon.name, "alpha");
+}
+
+#[test]
+fn serves_address_lookup() {
+    let mock = MockTincd::start(common::mesh()
+        .subnet("fd00::/64", "beta")).unwrap();
+    let state = Arc::new(RwLock::new(State { data: Some(common::load(&mock)), ..State::default() }));
+
+    let body = get(state.clone(), "/api/lookup/10.0.2.7");
+    let lookup: Lookup = serde_json::from_str(&body).unwrap();
+    assert_eq!(lookup.owner.unwrap(), "beta");
+
+    let body = get(state, "/api/lookup/fd00::1");
+    let lookup: Lookup = serde_json::from_str(&body).unwrap();
+    assert_eq!(lookup.subnet.to_string(), "fd00::/64");
+}
+
+#[test]
+fn serves_node_info() {
+    let mock = MockTincd::start(common::mesh()).unwrap();
+    let state = Arc::new(RwLock::new(State { data: Some(common::load(&mock)), ..State::default() }));
+
+    let body = get(state, "/api/nodes/beta");
+    let info: NodeInfo = serde_json::from_str(&body).unwrap();
+    assert_eq!(info.node.name, "beta");
+    assert_eq!(info.links.len(), 2);
+}
+
+#[test]
+fn serves_series() {
+    let mut series = Tsdb::default();
+    for time in [100, 200, 300].iter() {
+        series.record(*time, &[(SeriesId::new("node_reachable", "beta"), 1.0)]).save().unwrap();
+    }
+    let state = Arc::new(RwLock::new(State { series, ..State::default() }));
+
+    let body = get(state.clone(), "/api/series");
+    let list: Vec<SeriesId> = serde_json::from_str(&body).unwrap();
+    assert_eq!(list, vec![SeriesId::new("node_reachable", "beta")]);
+    let body = get(state, "/api/series/node_reachable?subject=beta&from=150&to=1000");
+    let series: Series = serde_json::from_str(&body).unwrap();
+    assert_eq!(series.resolution, Resolution::Raw);
+    assert_eq!(series.points.iter().map(|x| x.time).collect::<Vec<_>>(), vec![200, 300]);
+}
+
+#[test]
+fn serves_availability_csv() {
+    let mut series = Tsdb::default();
+    for (time, value) in [(0, 1.0), (20, 0.0), (40, 1.0)].iter() {
+        series.record(*time, &[(SeriesId::new("node_reachable", "beta"), *value)]).save().unwrap();
+    }
+    let state = Arc::new(RwLock::new(State { series, ..State::default() }));
+
+    let body = get(state, "/api/availability?from=0&to=100&format=csv");
+    assert_eq!(body.lines().nth(1), Some("node,beta,50.000,40,20,1,20,20"));
+}
@@ -1,5 +1,20 @@
 
 var dataURL = "data/nodes.json";
+var processURL = "api/daemon/process";
+var lookupURL = "api/lookup/";
+var conflictsURL = "api/conflicts";
+var auditURL = "api/audit";
+var nodeInfoURL = "api/nodes/";
+var traceURL = "api/trace/";
+var pmtuURL = "api/pmtu";
+var endpointsURL = "api/endpoints";
+var eventsURL = "api/events";
+var flapsURL = "api/flaps";
+var trafficURL = "api/traffic";
+var quotasURL = "api/quotas";
+var allowlistURL = "api/allowlist";
+var latencyURL = "api/latency";
+var seriesURL = "api/series";
 var autorefresh = true;
 var refreshInterval = 60;
 
@@ -8,6 +23,20 @@ var edges = null;
 var network = null;
 var scaleEdge = true;
 var refreshTimerID = null;
+var dataPaths = {};
+var metaPeers = [];
+var latency = {};
+var historyGraph = null;
+var overQuota = [];
+var overQuotaColor = "#E74C3C";
+var unknownNodes = [];
+var unknownNodeColor = "#C0392B";
+var dataPathColors = {
+    udp: "#2ECC71",
+    tcp: "#E67E22",
+    relayed: "#9B59B6",
+    pending: "#95A5A6"
+};
 
 function loadJSON(path, success, error) {
     var xhr = new XMLHttpRequest();
@@ -95,6 +124,18 @@ function startTimer(duration, display) {
 
 function reDraw() {
     loadJSON(dataURL,draw);
+    loadProcess();
+    loadConflicts();
+    loadAudit();
+    loadPmtu();
+    loadEndpoints();
+    loadEvents();
+    loadFlaps();
+    loadTraffic();
+    loadQuotas();
+    loadAllowlist();
+    loadLatency();
+    loadSeriesList();
     display = document.querySelector('#time');
     if (autorefresh == true) {
         startTimer(refreshInterval, display);
@@ -103,9 +144,25 @@ function reDraw() {
 
 function refresh() {
     loadJSON(dataURL,updateData);
+    loadProcess();
+    loadConflicts();
+    loadAudit();
+    loadPmtu();
+    loadEndpoints();
+    loadEvents();
+    loadFlaps();
+    loadTraffic();
+    loadQuotas();
+    loadAllowlist();
+    loadLatency();
+    loadSeriesList();
 }
 
 function updateData(jsonData) {
+    showDaemon(jsonData.daemon);
+    showInterface(jsonData.interface);
+    _loadDataPaths(jsonData.nodes);
+    showConnections(jsonData.connections);
     jsonData.nodes.forEach(function (n) {
         nodes.update(_createNode(n))
     });
@@ -113,6 +170,9 @@ function updateData(jsonData) {
     var _active_edges = [];
 
     jsonData.links.forEach(function (l) {
+        if (!_isDrawable(l)) {
+            return;
+        }
         _active_edges.push(l._hash);
         var _old_link = findEdgeByHash(l._hash, edges)
         if (_old_link.length == 0) {
@@ -129,12 +189,68 @@ function updateData(jsonData) {
     });
 }
 
+function _formatSubnet(s) {
+    var net = s.address;
+    if (s.type == "ipv4" && s.prefix != 32 || s.type == "ipv6" && s.prefix != 128) {
+        net += "/" + s.prefix;
+    }
+    return s.weight == 10 ? net : net + "#" + s.weight;
+}
+
+function _loadDataPaths(jsonNodes) {
+    dataPaths = {};
+    jsonNodes.forEach(function (n) {
+        dataPaths[n.name] = n.local ? "local" : n.data_path;
+    });
+}
+
+function _isMetaConnection(c) {
+    return !c.status.control;
+}
+
+function showConnections(connections) {
+    var meta = connections.filter(_isMetaConnection);
+    metaPeers = meta.map(function (c) { return c.name; });
+    document.querySelector('#connections').textContent = meta.length == 0 ? "none" : meta.map(function (c) {
+        var addr = c.address == null ? "unknown" : c.address + " port " + c.port;
+        return c.name + " (" + addr + ", fd " + c.socket + ", " + _formatFlags(c.status) + ")";
+    }).join(", ");
+}
+
+// Links carried by a meta connection of the local node.
+function _isMetaLink(l) {
+    var paths = [dataPaths[l.sname], dataPaths[l.tname]];
+    return paths.indexOf("local") != -1
+        && (metaPeers.indexOf(l.sname) != -1 || metaPeers.indexOf(l.tname) != -1);
+}
+
+function _getDataPathTitle(n) {
+    return n.relay == null ? n.data_path : n.data_path + " via " + n.relay;
+}
+
 function _createNode(n) {
     return {id: n.id,
             label: n.name,
-            color: _getNodeColor(n),
+            shape: n.local ? 'diamond' : 'dot',
+            color: {background: _getQuotaColor(n.name) || _getNodeColor(n),
+                    border: _getUnknownColor(n.name) || dataPathColors[n.data_path] || _getNodeColor(n)},
+            _background: _getNodeColor(n),
+            _border: dataPathColors[n.data_path] || _getNodeColor(n),
+            _borderWidth: dataPathColors[n.data_path] ? 3 : 1,
+            borderWidth: dataPathColors[n.data_path] || _getUnknownColor(n.name) ? 3 : 1,
+            shapeProperties: {borderDashes: _getUnknownColor(n.name) ? [4, 4] : false},
             reachable: n.reachable,
-            title: n.name + ' has ' + n.edges + ' edges.<br>Networks: ' + n.nets + '<br>Version: ' + n.version + '<br>Reachable: ' + n.reachable};
+            title: n.name + ' has ' + n.edges + ' edges.<br>Networks: ' + n.nets.map(_formatSubnet).join(', ')
+                + '<br>Version: ' + n.version + '<br>Reachable: ' + n.reachable
+                + '<br>Data path: ' + _getDataPathTitle(n)};
+}
+
+function _getUnknownColor(name) {
+    return unknownNodes.indexOf(name) != -1 ? unknownNodeColor : null;
+}
+
+function _getQuotaColor(name) {
+    return overQuota.indexOf(name) != -1 ? overQuotaColor : null;
 }
 
 function _getNodeColor(n) {
@@ -154,7 +270,8 @@ function _updateEdge(old_edge, l) {
     old_edge[0].color = getColor(100-l.frac*100);
     old_edge[0].width = _getEdgeWidth(l);
     old_edge[0].title = _getEdgeTitle(l);
-    old_edge[0].style = _getEdgeStyle(l);
+    old_edge[0].dashes = _getEdgeDashes(l);
+    old_edge[0].shadow = _isMetaLink(l);
     return old_edge;
 }
 
@@ -167,16 +284,53 @@ function _getEdgeWidth(l) {
         return 7;
 }
 
+function _getDirectionTitle(from, to, d) {
+    if (d == null) {
+        return from + " -> " + to + ": not announced";
+    }
+    var addr = d.address == null ? "unknown" : d.address + " port " + d.port;
+    return from + " -> " + to + ": " + addr + " (RT: " + d.weight/10 + "ms, options: " + d.options.toString(16) + ")";
+}
+
 function _getEdgeTitle(l) {
     var out = l.reachable == 0 ? "unreachable : " : "";
-    return out  + l.sname + " with " + l.tname + " (RT: "+l.weight/10+"ms)";
+    out = l.one_sided ? "one-sided : " + out : out;
+    return out  + l.sname + " with " + l.tname + " (RT: "+l.weight/10+"ms)"
+        + "<br>" + _getDirectionTitle(l.sname, l.tname, l.forward)
+        + "<br>" + _getDirectionTitle(l.tname, l.sname, l.reverse)
+        + _getLatencyTitle(latency[l._hash]);
 }
 
-function _getEdgeStyle(l) {
-    if (l.reachable == 0)
-        return 'dash-line';
-    else
-        return 'line';
+function _formatWindow(seconds) {
+    if (seconds % 86400 == 0) return seconds / 86400 + "d";
+    if (seconds % 3600 == 0) return seconds / 3600 + "h";
+    if (seconds % 60 == 0) return seconds / 60 + "m";
+    return seconds + "s";
+}
+
+function _getLatencyTitle(link) {
+    if (link == null) {
+        return "";
+    }
+    var ms = function (x) { return x.toFixed(1) + "ms"; };
+    return link.stats.map(function (s) {
+        return "<br>" + _formatWindow(s.window) + ": min " + ms(s.min) + ", avg " + ms(s.avg)
+            + ", max " + ms(s.max) + ", p95 " + ms(s.p95) + " (" + s.samples + " samples)";
+    }).join("") + (link.source == "weight" ? "<br>estimated from the edge weight" : "");
+}
+
+function _getEdgeDashes(l) {
+    if (l.reachable == 0 || l.one_sided)
+        return true;
+    // Dot the meta connections the local node also sends data over.
+    var paths = [dataPaths[l.sname], dataPaths[l.tname]];
+    if (paths.indexOf("local") != -1 && paths.indexOf("tcp") != -1)
+        return [2, 4];
+    return false;
+}
+
+function _isDrawable(l) {
+    return l.source != null && l.target != null;
 }
 
 function _createEdge(l) {
@@ -186,19 +340,510 @@ function _createEdge(l) {
             color: getColor(100-l.frac*100),
             width: _getEdgeWidth(l),
             title: _getEdgeTitle(l),
-            style: _getEdgeStyle(l)
+            dashes: _getEdgeDashes(l),
+            shadow: _isMetaLink(l)
            }
 }
 
+function showDaemon(daemon) {
+    var text = "unknown";
+    if (daemon != null) {
+        text = daemon.name + " (protocol " + daemon.protocol_major + "." + daemon.protocol_minor
+            + ", pid " + daemon.pid + ")";
+    }
+    document.querySelector('#daemon').textContent = text;
+}
+
+function _formatBytes(bytes) {
+    var units = ["B", "KiB", "MiB", "GiB"];
+    var i = 0;
+    while (bytes >= 1024 && i < units.length - 1) {
+        bytes /= 1024;
+        i++;
+    }
+    return bytes.toFixed(1) + " " + units[i];
+}
+
+function _formatDuration(secs) {
+    var days = Math.floor(secs / 86400);
+    var hours = Math.floor((secs % 86400) / 3600);
+    var minutes = Math.floor((secs % 3600) / 60);
+    return days + "d " + hours + "h " + minutes + "m";
+}
+
+function showProcess(p) {
+    var cpu = p.cpu_percent == null ? "-" : p.cpu_percent.toFixed(1) + "%";
+    var fds = p.open_fds == null ? "-" : p.open_fds;
+    document.querySelector('#process').textContent = "pid " + p.pid
+        + ", CPU " + cpu + " (" + (p.cpu_user_secs + p.cpu_system_secs).toFixed(1) + "s)"
+        + ", RSS " + _formatBytes(p.rss_bytes)
+        + ", fds " + fds
+        + ", threads " + p.threads
+        + ", up " + _formatDuration(p.uptime_secs);
+}
+
+function _formatRate(rate) {
+    return rate == null ? "-" : _formatBytes(rate) + "/s";
+}
+
+function showInterface(i) {
+    var text = "unknown";
+    if (i != null) {
+        text = i.name + ", MTU " + i.mtu
+            + ", rx " + _formatBytes(i.rx_bytes) + " (" + _formatRate(i.rx_rate) + ")"
+            + ", tx " + _formatBytes(i.tx_bytes) + " (" + _formatRate(i.tx_rate) + ")"
+            + ", errors " + i.rx_errors + "/" + i.tx_errors
+            + ", drops " + i.rx_dropped + "/" + i.tx_dropped;
+    }
+    document.querySelector('#interface').textContent = text;
+}
+
+function loadProcess() {
+    loadJSON(processURL, showProcess, function () {
+        document.querySelector('#process').textContent = "unknown";
+    });
+}
+
+function showPmtu(report) {
+    var problems = report.nodes.filter(function (n) {
+        return !n.converged || n.below_threshold;
+    }).map(function (n) {
+        return n.converged ? n.name + " " + n.pmtu + " < " + report.threshold
+            : n.name + " probing " + n.minmtu + "-" + n.maxmtu;
+    });
+    var text = report.nodes.length + " peers";
+    if (problems.length > 0) {
+        text += ", " + problems.join(", ");
+    }
+    if (report.changes.length > 0) {
+        var c = report.changes[report.changes.length - 1];
+        text += ", last change " + c.name + " " + c.from + " > " + c.to
+            + " at " + new Date(c.time * 1000).toLocaleString();
+    }
+    document.querySelector('#pmtu').textContent = text;
+}
+
+function loadPmtu() {
+    loadJSON(pmtuURL, showPmtu, function () {
+        document.querySelector('#pmtu').textContent = "unknown";
+    });
+}
+
+function showEndpoints(report) {
+    var nat = report.nodes.filter(function (n) { return n.behind_nat; });
+    var unknown = report.nodes.filter(function (n) { return n.unknown; });
+    var name = function (n) { return n.name; };
+    document.querySelector('#endpoints').textContent =
+        "behind NAT: " + (nat.length == 0 ? "none" : nat.map(name).join(", "))
+        + ", no known address: " + (unknown.length == 0 ? "none" : unknown.map(name).join(", "));
+}
+
+function loadEndpoints() {
+    loadJSON(endpointsURL, showEndpoints, function () {
+        document.querySelector('#endpoints').textContent = "unknown";
+    });
+}
+
+function showLatency(report) {
+    latency = {};
+    report.links.forEach(function (link) {
+        latency[link._hash] = link;
+    });
+    if (edges == null) {
+        return;
+    }
+    edges.forEach(function (edge) {
+        var link = latency[edge._hash];
+        if (link != null) {
+            var title = edge.title.split("<br>").slice(0, 3).join("<br>") + _getLatencyTitle(link);
+            edges.update({id: edge.id, title: title});
+        }
+    });
+}
+
+function loadLatency() {
+    loadJSON(latencyURL, showLatency, function () {
+        latency = {};
+    });
+}
+
+function showSeriesList(list) {
+    var select = document.querySelector('#series');
+    var selected = select.value;
+    select.innerHTML = "";
+    list.forEach(function (id) {
+        var option = document.createElement("option");
+        option.value = JSON.stringify(id);
+        option.textContent = id.subject == "" ? id.metric : id.metric + " " + id.subject;
+        select.appendChild(option);
+    });
+    if (selected != "") {
+        select.value = selected;
+    }
+    loadSeries();
+}
+
+function loadSeriesList() {
+    loadJSON(seriesURL, showSeriesList, function () {});
+}
+
+function showSeries(series) {
+    var items = series.points.map(function (p) {
+        return {x: new Date(p.time * 1000), y: p.value};
+    });
+    if (historyGraph == null) {
+        var container = document.querySelector('#history');
+        historyGraph = new vis.Graph2d(container, items, {height: "200px", drawPoints: false});
+    }
+    else {
+        historyGraph.setItems(items);
+    }
+    historyGraph.fit();
+}
+
+function loadSeries() {
+    var selected = document.querySelector('#series').value;
+    if (selected == "") {
+        return;
+    }
+    var id = JSON.parse(selected);
+    var range = parseInt(document.querySelector('#series-range').value);
+    var from = Math.floor(Date.now() / 1000) - range;
+    loadJSON(seriesURL + "/" + id.metric + "?subject=" + encodeURIComponent(id.subject) + "&from=" + from,
+             showSeries, function () {});
+}
+
+function showFlaps(report) {
+    var flapping = report.states.filter(function (s) { return s.flapping; });
+    document.querySelector('#flaps').textContent = flapping.length == 0 ? "none"
+        : flapping.map(function (s) {
+            return s.subject + " " + s.name + " (" + s.score + " changes in " + report.window + "s)";
+        }).join(", ");
+}
+
+function loadFlaps() {
+    loadJSON(flapsURL, showFlaps, function () {
+        document.querySelector('#flaps').textContent = "unknown";
+    });
+}
+
+function showTraffic(report) {
+    var top = report.nodes.map(function (n) {
+        return n.name + " " + _formatBytes(n.in_bytes + n.out_bytes);
+    });
+    document.querySelector('#traffic').textContent = _formatBytes(report.total.in_bytes + report.total.out_bytes)
+        + (top.length == 0 ? "" : ", top: " + top.join(", "));
+}
+
+function loadTraffic() {
+    loadJSON(trafficURL + "?limit=5", showTraffic, function () {
+        document.querySelector('#traffic').textContent = "unknown";
+    });
+}
+
+function _formatQuota(n) {
+    var parts = [];
+    if (n.daily != null) {
+        parts.push("day " + n.daily.percent.toFixed(0) + "% of " + _formatBytes(n.daily.limit));
+    }
+    if (n.monthly != null) {
+        parts.push("month " + n.monthly.percent.toFixed(0) + "% of " + _formatBytes(n.monthly.limit));
+    }
+    if (n.over_rate) {
+        parts.push(_formatBytes(n.rate) + "/s over " + _formatBytes(n.max_rate) + "/s");
+    }
+    return n.name + " (" + parts.join(", ") + ")";
+}
+
+function showQuotas(report) {
+    overQuota = report.nodes.filter(function (n) { return n.over_quota; }).map(function (n) { return n.name; });
+    var warned = report.nodes.filter(function (n) {
+        return n.over_rate || [n.daily, n.monthly].some(function (u) { return u != null && u.level != null; });
+    });
+    document.querySelector('#quotas').textContent = report.nodes.length == 0 ? "none configured"
+        : warned.length == 0 ? "all within limits" : warned.map(_formatQuota).join(", ");
+    if (nodes == null) {
+        return;
+    }
+    nodes.forEach(function (node) {
+        var background = _getQuotaColor(node.label) || node._background;
+        if (node.color.background != background) {
+            nodes.update({id: node.id, color: {background: background, border: node.color.border}});
+        }
+    });
+}
+
+function loadQuotas() {
+    loadJSON(quotasURL, showQuotas, function () {
+        document.querySelector('#quotas').textContent = "unknown";
+    });
+}
+
+function showAllowlist(report) {
+    unknownNodes = report.unknown;
+    var problems = [];
+    if (report.unknown.length > 0) {
+        problems.push("unknown " + report.unknown.join(", "));
+    }
+    if (report.never_seen.length > 0) {
+        problems.push("never seen " + report.never_seen.join(", "));
+    }
+    if (report.unreachable.length > 0) {
+        problems.push("unreachable " + report.unreachable.join(", "));
+    }
+    if (report.vanished.length > 0) {
+        problems.push("vanished " + report.vanished.map(function (n) {
+            return n.name + " (last seen " + new Date(n.last_seen * 1000).toLocaleString() + ")";
+        }).join(", "));
+    }
+    document.querySelector('#allowlist').textContent = report.source == null ? "none configured"
+        : problems.length == 0 ? "all " + report.expected + " nodes as expected" : problems.join("; ");
+    if (nodes == null) {
+        return;
+    }
+    nodes.forEach(function (node) {
+        var border = _getUnknownColor(node.label) || node._border;
+        if (node.color.border != border) {
+            nodes.update({id: node.id, color: {background: node.color.background, border: border},
+                          borderWidth: border == unknownNodeColor ? 3 : node._borderWidth,
+                          shapeProperties: {borderDashes: border == unknownNodeColor ? [4, 4] : false}});
+        }
+    });
+}
+
+function loadAllowlist() {
+    loadJSON(allowlistURL, showAllowlist, function () {
+        document.querySelector('#allowlist').textContent = "unknown";
+    });
+}
+
+function _formatEvent(e) {
+    var text = new Date(e.time * 1000).toLocaleString() + " " + e.node + ": ";
+    var link = e.peer == null ? "" : "link to " + e.peer + " ";
+    switch (e.kind) {
+    case "endpoint_changed":
+        return text + "endpoint " + e.from + " > " + e.to;
+    case "reachable":
+        return text + "reachable";
+    case "unreachable":
+        return text + "unreachable";
+    case "link_added":
+        return text + "link to " + e.peer + " added";
+    case "link_removed":
+        return text + "link to " + e.peer + " removed";
+    case "quota_warning":
+        return text + e.level + "% of the " + (e.period == "day" ? "daily" : "monthly") + " quota used";
+    case "rate_exceeded":
+        return text + _formatBytes(e.rate) + "/s over the " + _formatBytes(e.limit) + "/s limit";
+    case "unknown_node":
+        return text + "unknown node " + (e.reachable ? "reachable" : "announced");
+    case "flap_started":
+        return text + link + "flapping, " + e.score + " changes";
+    case "flap_stopped":
+        return text + link + "stable " + (e.up ? "up" : "down") + ", " + e.suppressed + " changes suppressed";
+    }
+    return text + e.kind;
+}
+
+function showEvents(log) {
+    document.querySelector('#events').textContent =
+        log.events.slice(-10).reverse().map(_formatEvent).join("\n");
+}
+
+function loadEvents() {
+    loadJSON(eventsURL, showEvents, function () {
+        document.querySelector('#events').textContent = "";
+    });
+}
+
+function showConflicts(report) {
+    var badge = document.querySelector('#conflicts');
+    if (report.conflicts.length == 0) {
+        badge.style.display = "none";
+        return;
+    }
+    badge.textContent = report.conflicts.length + " subnet conflicts";
+    badge.title = report.conflicts.map(function (c) {
+        return c.kind + ": " + _formatSubnet(c.subnet) + " (" + c.subnet.owner + ") and "
+            + _formatSubnet(c.other) + " (" + c.other.owner + ")";
+    }).join("\n");
+    badge.style.display = "inline";
+}
+
+function loadConflicts() {
+    loadJSON(conflictsURL, showConflicts, function () {
+        document.querySelector('#conflicts').style.display = "none";
+    });
+}
+
+function _formatFinding(f) {
+    switch (f.kind) {
+    case "weak_cipher":
+        return f.node + ": weak cipher " + f.cipher;
+    case "weak_digest":
+        return f.node + ": weak digest " + f.digest;
+    case "short_mac":
+        return f.node + ": " + f.length + " byte MAC";
+    }
+    return f.node + ": " + f.kind.replace(/_/g, " ");
+}
+
+function showAudit(report) {
+    document.querySelector('#protocols').textContent = report.versions.length == 0 ? "no reachable peers"
+        : report.versions.map(function (v) {
+            return "1." + v.protocol_minor + (v.sptps ? " SPTPS" : " legacy") + ": " + v.nodes.join(", ");
+        }).join("; ");
+    var badge = document.querySelector('#audit');
+    if (report.findings.length == 0) {
+        badge.style.display = "none";
+        return;
+    }
+    var critical = report.findings.some(function (f) { return f.severity == "critical"; });
+    badge.className = critical ? "badge" : "badge warning";
+    badge.textContent = report.findings.length + " security findings";
+    badge.title = report.findings.map(_formatFinding).join("\n");
+    badge.style.display = "inline";
+}
+
+function loadAudit() {
+    loadJSON(auditURL, showAudit, function () {
+        document.querySelector('#audit').style.display = "none";
+    });
+}
+
+function _formatFlags(flags) {
+    return Object.keys(flags).filter(function (k) {
+        return flags[k] === true;
+    }).join(" ");
+}
+
+function showNodeInfo(info) {
+    var n = info.node;
+    var lines = [
+        n.name + (n.local ? " (local)" : ""),
+        "Address:     " + (n.address == null ? "unknown" : n.address + " port " + n.port),
+        "Status:      " + _formatFlags(n.status),
+        "Options:     " + _formatFlags(n.options) + " (protocol 17." + n.options.protocol_minor + ")",
+        "Cipher:      " + n.cipher + ", digest " + n.digest + ", MAC length " + n.maclength
+            + ", compression " + n.compression,
+        "Route:       nexthop " + n.nexthop + ", via " + n.via + ", distance " + n.distance,
+        "Data path:   " + _getDataPathTitle(n),
+        "PMTU:        " + n.pmtu + " (min " + n.minmtu + ", max " + n.maxmtu + ")",
+        "Last change: " + new Date(n.last_state_change * 1000).toLocaleString(),
+        "Subnets:     " + n.nets.map(_formatSubnet).join(", "),
+        "Edges:       " + info.links.map(function (l) {
+            return (l.sname == n.name ? l.tname : l.sname) + " (" + l.weight + ")";
+        }).join(", "),
+        "Connection:  " + (info.connection == null ? "none"
+            : (info.connection.address || "unknown") + " port " + info.connection.port
+                + ", socket " + info.connection.socket + ", " + _formatFlags(info.connection.status)
+            + ", options " + _formatFlags(info.connection.options)),
+    ];
+    if (n.traffic != null) {
+        lines.push("Traffic:     in " + n.traffic.in_packets + " packets, " + _formatBytes(n.traffic.in_bytes)
+            + ", out " + n.traffic.out_packets + " packets, " + _formatBytes(n.traffic.out_bytes));
+    }
+    document.querySelector('#node-info').textContent = lines.join("\n");
+    document.querySelector('#node-panel').style.display = "block";
+}
+
+function loadNodeInfo(name) {
+    loadJSON(nodeInfoURL + encodeURIComponent(name), showNodeInfo, function () {
+        document.querySelector('#node-panel').style.display = "none";
+    });
+}
+
+function _formatPath(p) {
+    return p == null ? "none" : p.hops.join(" > ") + " (weight " + p.weight + ")";
+}
+
+function showTrace(t) {
+    var text = "route " + _formatPath(t.route);
+    if (t.via != null && t.via != t.destination) {
+        text += ", relayed via " + t.via;
+    }
+    if (JSON.stringify(t.route) != JSON.stringify(t.shortest)) {
+        text += ", shortest " + _formatPath(t.shortest);
+    }
+    document.querySelector('#lookup').textContent = text;
+
+    var hops = t.route == null ? [t.destination] : t.route.hops;
+    var path_nodes = nodes.get({
+        filter: function (n) {
+            return hops.indexOf(n.label) != -1;
+        }
+    });
+    var path_edges = [];
+    for (var i = 1; i < hops.length; i++) {
+        var pair = [hops[i - 1], hops[i]].sort();
+        findEdgeByHash(pair[0] + "-" + pair[1], edges).forEach(function (e) {
+            path_edges.push(e.id);
+        });
+    }
+    network.setSelection({
+        nodes: path_nodes.map(function (n) { return n.id; }),
+        edges: path_edges
+    });
+}
+
+function traceRoute() {
+    var destination = document.querySelector('#lookup-address').value.trim();
+    if (destination == "") {
+        return;
+    }
+    loadJSON(traceURL + encodeURI(destination), showTrace, function (xhr) {
+        var text = "trace failed";
+        try {
+            text = JSON.parse(xhr.responseText).error;
+        } catch (e) {}
+        document.querySelector('#lookup').textContent = text;
+    });
+}
+
+function showLookup(l) {
+    var owner = l.owner == null ? "broadcast" : l.owner;
+    var text = _formatSubnet(l.subnet) + " owned by " + owner;
+    if (l.owner != null) {
+        text += l.reachable ? ", reachable" : ", unreachable";
+        if (l.nexthop != null) {
+            text += " via " + l.via + ", nexthop " + l.nexthop;
+        }
+    }
+    document.querySelector('#lookup').textContent = text;
+    nodes.forEach(function (n) {
+        if (n.label == l.owner) {
+            network.selectNodes([n.id]);
+        }
+    });
+}
+
+function lookup() {
+    var address = document.querySelector('#lookup-address').value.trim();
+    if (address == "") {
+        return;
+    }
+    loadJSON(lookupURL + encodeURI(address), showLookup, function (xhr) {
+        var text = "lookup failed";
+        try {
+            text = JSON.parse(xhr.responseText).error;
+        } catch (e) {}
+        document.querySelector('#lookup').textContent = text;
+    });
+}
+
 function draw(jsonData) {
+    showDaemon(jsonData.daemon);
+    showInterface(jsonData.interface);
     nodes = new vis.DataSet();
     edges = new vis.DataSet();
 
+    _loadDataPaths(jsonData.nodes);
+    showConnections(jsonData.connections);
     jsonData.nodes.forEach(function (n) {
         nodes.add(_createNode(n))
     });
 
-    jsonData.links.forEach(function (l) {
+    jsonData.links.filter(_isDrawable).forEach(function (l) {
         edges.add(_createEdge(l));
     });
 
@@ -225,4 +870,9 @@ function draw(jsonData) {
     };
     var ids = edges.getIds();
     network = new vis.Network(container, data, options);
+    network.on("click", function (params) {
+        if (params.nodes.length > 0) {
+            loadNodeInfo(nodes.get(params.nodes[0]).label);
+        }
+    });
 }